@@ -0,0 +1,204 @@
+use crate::parser::ast_struct::WidgetDecl;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// the core Material widgets, shipped with the compiler
+const MATERIAL_CATALOG: &str = include_str!("material.json");
+
+/// How many widgets a widget takes as children, as opposed to slots.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChildArity {
+    // Text, Icon, Scaffold: everything goes through named arguments or slots
+    #[default]
+    None,
+    // Container: `child:`, which may be left out
+    Optional,
+    // Expanded: a `child:` that is required
+    One,
+    // Column: `children: [...]`
+    Many,
+}
+
+impl ChildArity {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            ChildArity::None => "no children",
+            ChildArity::Optional => "at most one child",
+            ChildArity::One => "exactly one child",
+            ChildArity::Many => "any number of children",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ParamSpec {
+    pub name: String,
+    // the Dart type, only shown to the user
+    #[serde(rename = "type")]
+    pub ty: String,
+    #[serde(default)]
+    pub required: bool,
+}
+
+/// What a Flutter widget's constructor takes.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WidgetSpec {
+    // given by the markup as the tag's content: <Text> "Hi"
+    #[serde(default)]
+    pub positional: Vec<ParamSpec>,
+    // named arguments that are not widgets: props, attributes and events
+    #[serde(default)]
+    pub params: Vec<ParamSpec>,
+    #[serde(default)]
+    pub children: ChildArity,
+    // named arguments taking a widget, filled by children with `slot="name"`
+    #[serde(default)]
+    pub slots: Vec<ParamSpec>,
+    // the slot a lone unslotted child goes to, for widgets taking no `child:`:
+    // the `<Text>` under an `<AppBar>` is its `title:`
+    #[serde(default)]
+    pub default_slot: Option<String>,
+    // the constructor building children lazily from `itemCount:` and `itemBuilder:`,
+    // used when the children come from a single `<for>`
+    #[serde(default)]
+    pub builder: Option<String>,
+}
+
+impl WidgetSpec {
+    /// The constructor generated for a markup declaration, which requires every
+    /// param and takes no children.
+    pub fn declared(decl: &WidgetDecl) -> Self {
+        let params = decl
+            .params
+            .iter()
+            .map(|param| ParamSpec {
+                name: param.name.clone(),
+                ty: param.ty.clone(),
+                required: true,
+            })
+            .collect();
+
+        WidgetSpec {
+            params,
+            ..WidgetSpec::default()
+        }
+    }
+
+    /// The named argument `name`, either a param or a slot.
+    pub fn named(&self, name: &str) -> Option<&ParamSpec> {
+        self.params
+            .iter()
+            .chain(&self.slots)
+            .find(|param| param.name == name)
+    }
+
+    // Text(String data, {TextStyle? style, ...})
+    pub fn signature(&self, widget: &str) -> String {
+        let describe = |param: &ParamSpec| {
+            let required = if param.required { "required " } else { "" };
+            format!("{}{} {}", required, param.ty, param.name)
+        };
+
+        let mut args: Vec<String> = self
+            .positional
+            .iter()
+            .map(|param| format!("{} {}", param.ty, param.name))
+            .collect();
+        let mut named: Vec<String> = self
+            .params
+            .iter()
+            .chain(&self.slots)
+            .map(describe)
+            .collect();
+        match self.children {
+            ChildArity::None => {}
+            ChildArity::Optional => named.push(String::from("Widget? child")),
+            ChildArity::One => named.push(String::from("required Widget child")),
+            ChildArity::Many => named.push(String::from("List<Widget> children")),
+        }
+        if !named.is_empty() {
+            args.push(format!("{{{}}}", named.join(", ")));
+        }
+
+        format!("{}({})", widget, args.join(", "))
+    }
+}
+
+/// The Flutter widgets the compiler knows the constructor of, to which compiling
+/// a file adds the ones declared in markup. Widgets missing from it are passed
+/// through unchecked.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Catalog {
+    pub widgets: BTreeMap<String, WidgetSpec>,
+}
+
+impl Default for Catalog {
+    fn default() -> Self {
+        Catalog::material()
+    }
+}
+
+impl Catalog {
+    pub fn material() -> Self {
+        Catalog::from_json(MATERIAL_CATALOG).expect("the bundled catalog is valid")
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    pub fn get(&self, widget: &str) -> Option<&WidgetSpec> {
+        self.widgets.get(widget)
+    }
+
+    /// Adds the widgets of `other`, replacing the ones known under the same name.
+    pub fn extend(&mut self, other: Catalog) {
+        self.widgets.extend(other.widgets);
+    }
+}
+
+#[test]
+fn load_the_material_catalog() {
+    let catalog = Catalog::material();
+
+    assert_eq!(catalog.get("Column").unwrap().children, ChildArity::Many);
+    assert_eq!(
+        catalog.get("Container").unwrap().children,
+        ChildArity::Optional
+    );
+    assert!(catalog.get("Scaffold").unwrap().named("appBar").is_some());
+    assert_eq!(
+        catalog.get("AppBar").unwrap().default_slot.as_deref(),
+        Some("title")
+    );
+    assert!(catalog.get("Text").unwrap().positional[0].required);
+    assert_eq!(
+        catalog.get("ListView").unwrap().builder.as_deref(),
+        Some("ListView.builder")
+    );
+}
+
+#[test]
+fn reject_unknown_fields() {
+    let json = r#"{"widgets": {"Text": {"childs": "many"}}}"#;
+
+    assert!(Catalog::from_json(json).is_err());
+}
+
+#[test]
+fn describe_constructor_signatures() {
+    let catalog = Catalog::material();
+
+    assert_eq!(
+        catalog.get("Padding").unwrap().signature("Padding"),
+        "Padding({required EdgeInsetsGeometry padding, Widget? child})"
+    );
+    assert_eq!(
+        catalog.get("Icon").unwrap().signature("Icon"),
+        "Icon(IconData? icon, {double? size, Color? color, String? semanticLabel})"
+    );
+}
@@ -0,0 +1,178 @@
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+usage: wdart <command> [options] <paths>...
+       wdart lsp
+
+commands:
+  build   compile each .flutter file to a .dart file next to it
+  check   report diagnostics without writing anything
+  tokens  print the token stream of each file
+  ast     print the parse tree of each file
+  watch   build, then rebuild the files that change and their importers
+  fmt     rewrite each file with canonical indentation and layout
+  lsp     run the language server on stdin and stdout, for editors
+
+options:
+  -o, --out-dir <dir>  write compiled files into <dir> instead (build and watch)
+  --format <format>    `debug` (default) or `json`, one document per line (tokens and ast)
+  --check              fail on files that aren't formatted instead of rewriting them (fmt)
+  -h, --help           print this message
+";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Build { out_dir: Option<PathBuf> },
+    Check,
+    Tokens,
+    Ast,
+    Watch { out_dir: Option<PathBuf> },
+    Fmt { check: bool },
+    Lsp,
+}
+
+// how `tokens` and `ast` print what they dump
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Format {
+    #[default]
+    Debug,
+    Json,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Args {
+    pub command: Command,
+    pub format: Format,
+    // files and directories, directories are searched for .flutter files
+    pub paths: Vec<PathBuf>,
+}
+
+/// What the command line asks for, or `None` when it only asks for help.
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Option<Args>, String> {
+    let mut args = args.into_iter();
+
+    let mut command = match args.next().as_deref() {
+        None | Some("-h" | "--help" | "help") => return Ok(None),
+        Some("build") => Command::Build { out_dir: None },
+        Some("check") => Command::Check,
+        Some("tokens") => Command::Tokens,
+        Some("ast") => Command::Ast,
+        Some("watch") => Command::Watch { out_dir: None },
+        Some("fmt") => Command::Fmt { check: false },
+        Some("lsp") => Command::Lsp,
+        Some(other) => return Err(format!("unknown command `{}`", other)),
+    };
+
+    let mut format = Format::default();
+    let mut paths = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "-o" | "--out-dir" => match &mut command {
+                Command::Build { out_dir } | Command::Watch { out_dir } => {
+                    let dir = args
+                        .next()
+                        .ok_or_else(|| format!("`{}` expects a directory", arg))?;
+                    *out_dir = Some(PathBuf::from(dir));
+                }
+                _ => return Err(format!("`{}` is only accepted by `build` and `watch`", arg)),
+            },
+            "--check" => match &mut command {
+                Command::Fmt { check } => *check = true,
+                _ => return Err(format!("`{}` is only accepted by `fmt`", arg)),
+            },
+            "--format" => {
+                if !matches!(command, Command::Tokens | Command::Ast) {
+                    return Err(format!("`{}` is only accepted by `tokens` and `ast`", arg));
+                }
+                format = match args.next().as_deref() {
+                    Some("debug") => Format::Debug,
+                    Some("json") => Format::Json,
+                    Some(other) => return Err(format!("unknown format `{}`", other)),
+                    None => return Err(format!("`{}` expects `debug` or `json`", arg)),
+                };
+            }
+            flag if flag.starts_with('-') => return Err(format!("unknown option `{}`", flag)),
+            path => paths.push(PathBuf::from(path)),
+        }
+    }
+
+    // the editor sends the files over the protocol
+    match (&command, paths.is_empty()) {
+        (Command::Lsp, false) => return Err(String::from("`lsp` takes no paths")),
+        (Command::Lsp, true) => {}
+        (_, true) => return Err(String::from("no input files or directories given")),
+        (_, false) => {}
+    }
+
+    Ok(Some(Args {
+        command,
+        format,
+        paths,
+    }))
+}
+
+#[cfg(test)]
+fn parse(args: &[&str]) -> Result<Option<Args>, String> {
+    parse_args(args.iter().map(|arg| arg.to_string()))
+}
+
+#[test]
+fn parse_build_with_out_dir() {
+    assert_eq!(
+        parse(&["build", "lib", "-o", "gen"]),
+        Ok(Some(Args {
+            command: Command::Build {
+                out_dir: Some(PathBuf::from("gen"))
+            },
+            format: Format::Debug,
+            paths: vec![PathBuf::from("lib")],
+        }))
+    );
+}
+
+#[test]
+fn parse_json_format() {
+    assert_eq!(
+        parse(&["ast", "--format", "json", "app.flutter"]),
+        Ok(Some(Args {
+            command: Command::Ast,
+            format: Format::Json,
+            paths: vec![PathBuf::from("app.flutter")],
+        }))
+    );
+}
+
+#[test]
+fn parse_fmt_check() {
+    assert_eq!(
+        parse(&["fmt", "--check", "lib"]).map(|args| args.map(|args| args.command)),
+        Ok(Some(Command::Fmt { check: true }))
+    );
+}
+
+#[test]
+fn parse_lsp_without_paths() {
+    assert_eq!(
+        parse(&["lsp"]).map(|args| args.map(|args| args.command)),
+        Ok(Some(Command::Lsp))
+    );
+}
+
+#[test]
+fn parse_help_anywhere() {
+    assert_eq!(parse(&[]), Ok(None));
+    assert_eq!(parse(&["check", "--help"]), Ok(None));
+}
+
+#[test]
+fn reject_bad_command_lines() {
+    assert!(parse(&["compile", "app.flutter"]).is_err());
+    assert!(parse(&["check"]).is_err());
+    assert!(parse(&["check", "-o", "gen", "app.flutter"]).is_err());
+    assert!(parse(&["build", "app.flutter", "--out-dir"]).is_err());
+    assert!(parse(&["build", "--format", "json", "app.flutter"]).is_err());
+    assert!(parse(&["ast", "--format", "yaml", "app.flutter"]).is_err());
+    assert!(parse(&["check", "--check", "app.flutter"]).is_err());
+    assert!(parse(&["lsp", "app.flutter"]).is_err());
+}
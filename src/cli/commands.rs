@@ -0,0 +1,388 @@
+use super::{
+    args::{Args, Command, Format},
+    watch::watch,
+};
+use crate::{
+    catalog::catalog::{Catalog, WidgetSpec},
+    config::config::Config,
+    diagnostics::diagnostic::Diagnostic,
+    emitter::emitter::emit_program,
+    formatter::formatter::format,
+    guard_clause,
+    lexer::{lexer::lex_with, token_struct::Token},
+    lsp::server::serve,
+    parser::{ast_struct::Item, parser::parse_program},
+    resolver::resolver::{resolve_module, Module, MARKUP_EXTENSION},
+    schema::document::{AstDocument, TokensDocument},
+};
+use std::{
+    collections::VecDeque,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+pub const EXIT_OK: u8 = 0;
+// at least one file has errors
+pub const EXIT_FAILED: u8 = 1;
+// the command line itself is wrong
+pub const EXIT_USAGE: u8 = 2;
+
+/// A markup file found from one of the paths given on the command line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Source {
+    pub path: PathBuf,
+    // relative to the directory it was found in, used to lay out `--out-dir`
+    pub relative: PathBuf,
+}
+
+pub fn run(args: &Args) -> u8 {
+    match &args.command {
+        Command::Watch { out_dir } => return watch(&args.paths, out_dir.as_deref()),
+        Command::Lsp => return serve(io::stdin().lock(), io::stdout().lock()),
+        _ => {}
+    }
+
+    let mut sources = Vec::new();
+    for path in &args.paths {
+        match find_sources(path) {
+            Ok(mut found) => sources.append(&mut found),
+            Err(err) => {
+                eprintln!("error: cannot read {}: {}", path.display(), err);
+                return EXIT_FAILED;
+            }
+        }
+    }
+
+    let mut failed = 0;
+    for source in &sources {
+        // headers only make the output harder to pipe when there is a single file,
+        // and JSON documents are already one per line
+        let dump = matches!(args.command, Command::Tokens | Command::Ast);
+        if sources.len() > 1 && dump && args.format == Format::Debug {
+            println!("==> {} <==", source.path.display());
+        }
+        if let Err(message) = run_file(args, source) {
+            eprint!("{}", message);
+            failed += 1;
+        }
+    }
+
+    if failed > 0 {
+        eprintln!("error: {} of {} files failed", failed, sources.len());
+        return EXIT_FAILED;
+    }
+    EXIT_OK
+}
+
+// the error is ready to print, either a rendered diagnostic or an I/O failure
+fn run_file(args: &Args, source: &Source) -> Result<(), String> {
+    let file = source.path.display().to_string();
+    let text = fs::read_to_string(&source.path)
+        .map_err(|err| format!("error: cannot read {}: {}\n", file, err))?;
+    let config = Config::discover(&source.path)?;
+    let render = |diagnostic: Diagnostic| diagnostic.render(&text);
+    let render_all = |diagnostics: Vec<Diagnostic>| {
+        diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.render(&text))
+            .collect::<String>()
+    };
+
+    match (&args.command, args.format) {
+        (Command::Tokens, Format::Debug) => {
+            for token in lex_file(&file, &text, &config).map_err(render)? {
+                println!(
+                    "{}:{}..{} {:?}",
+                    token.line, token.start, token.end, token.kind
+                );
+            }
+        }
+        (Command::Tokens, Format::Json) => {
+            let document =
+                TokensDocument::new(&file, lex_file(&file, &text, &config).map_err(render)?);
+            println!("{}", to_json(&document)?);
+        }
+        // the tree is printed even when partial, the diagnostics explain the holes
+        (Command::Ast, Format::Debug) => {
+            let (items, diagnostics) = parse_file(&file, &text, &config);
+            println!("{:#?}", items);
+            guard_clause!(!diagnostics.is_empty(), Err(render_all(diagnostics)));
+        }
+        (Command::Ast, Format::Json) => {
+            let (items, diagnostics) = parse_file(&file, &text, &config);
+            let document = AstDocument::new(&file, items, diagnostics.clone());
+            println!("{}", to_json(&document)?);
+            guard_clause!(!diagnostics.is_empty(), Err(render_all(diagnostics)));
+        }
+        (Command::Check, _) => {
+            compile(&file, &text, &config).map_err(render_all)?;
+        }
+        // served before any file is read
+        (Command::Lsp, _) => {}
+        // files that don't parse are left alone, their layout can't be trusted
+        (Command::Fmt { check }, _) => {
+            let tokens = lex_file(&file, &text, &config).map_err(render)?;
+            let (_, diagnostics) = parse_file(&file, &text, &config);
+            guard_clause!(!diagnostics.is_empty(), Err(render_all(diagnostics)));
+
+            let formatted = format(&text, tokens, &config.format_options());
+            guard_clause!(formatted == text, Ok(()));
+            if *check {
+                return Err(format!("error: {} is not formatted\n", file));
+            }
+            fs::write(&source.path, formatted)
+                .map_err(|err| format!("error: cannot write {}: {}\n", file, err))?;
+        }
+        (Command::Build { out_dir } | Command::Watch { out_dir }, _) => {
+            let dart = compile(&file, &text, &config).map_err(render_all)?;
+            let out_dir = out_dir.clone().or_else(|| config.out_dir());
+            let target = output_path(source, out_dir.as_deref());
+            write_output(&target, &dart)
+                .map_err(|err| format!("error: cannot write {}: {}\n", target.display(), err))?;
+        }
+    }
+
+    Ok(())
+}
+
+fn to_json(document: &impl serde::Serialize) -> Result<String, String> {
+    serde_json::to_string(document).map_err(|err| format!("error: {}\n", err))
+}
+
+pub fn lex_file(file: &str, source: &str, config: &Config) -> Result<Vec<Token>, Diagnostic> {
+    lex_with(source, &config.lex_options()).map_err(|err| err.to_diagnostic(file))
+}
+
+/// The parse tree of a file, partial when there are diagnostics.
+pub fn parse_file(file: &str, source: &str, config: &Config) -> (Vec<Item>, Vec<Diagnostic>) {
+    let tokens = match lex_file(file, source, config) {
+        Ok(tokens) => tokens,
+        Err(diagnostic) => return (Vec::new(), vec![diagnostic]),
+    };
+
+    let program = parse_program(&mut VecDeque::from(tokens));
+    let diagnostics = program
+        .errors
+        .iter()
+        .map(|err| err.to_diagnostic(file, source))
+        .collect();
+    (program.items, diagnostics)
+}
+
+/// Compiles a file to Dart, checking the widgets it uses against the catalog and
+/// the declarations of the markup files it imports.
+pub fn compile(file: &str, source: &str, config: &Config) -> Result<String, Vec<Diagnostic>> {
+    let (items, diagnostics) = parse_file(file, source, config);
+    guard_clause!(!diagnostics.is_empty(), Err(diagnostics));
+
+    let path = Path::new(file);
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    // the graph knows the file by its canonical path, the user by the one given
+    let reported = |diagnostic: Diagnostic| {
+        vec![Diagnostic {
+            file: file.to_string(),
+            ..diagnostic
+        }]
+    };
+    let graph = resolve_module(Module {
+        path: path.clone(),
+        source: source.to_string(),
+        items,
+    })
+    .map_err(reported)?;
+    let declared = graph
+        .visible_widgets(&path)
+        .map_err(reported)?
+        .into_iter()
+        .map(|(name, decl)| (name.to_string(), WidgetSpec::declared(decl)))
+        .collect();
+
+    let mut options = config.emit_options();
+    options.catalog.extend(Catalog { widgets: declared });
+    let module = graph.get(&path).expect("the entry is part of its graph");
+    emit_program(&module.items, &options).map_err(|err| vec![err.to_diagnostic(file)])
+}
+
+/// The `.flutter` files a command line path stands for: the file itself, or every
+/// markup file below a directory, in a stable order.
+pub fn find_sources(path: &Path) -> io::Result<Vec<Source>> {
+    if !path.is_dir() {
+        // fail early on missing files instead of when reading them
+        fs::metadata(path)?;
+        let relative = PathBuf::from(path.file_name().unwrap_or(path.as_os_str()));
+        return Ok(vec![Source {
+            path: path.to_path_buf(),
+            relative,
+        }]);
+    }
+
+    let mut sources = Vec::new();
+    let mut pending = vec![path.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let entry_path = entry?.path();
+            if entry_path.is_dir() {
+                pending.push(entry_path);
+            } else if entry_path.extension().and_then(|ext| ext.to_str()) == Some(MARKUP_EXTENSION)
+            {
+                let relative = entry_path.strip_prefix(path).unwrap_or(&entry_path);
+                sources.push(Source {
+                    relative: relative.to_path_buf(),
+                    path: entry_path,
+                });
+            }
+        }
+    }
+
+    sources.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(sources)
+}
+
+// `app.flutter` compiles to `app.dart`, next to it or at the same place under `out_dir`
+pub fn output_path(source: &Source, out_dir: Option<&Path>) -> PathBuf {
+    let path = match out_dir {
+        Some(dir) => dir.join(&source.relative),
+        None => source.path.clone(),
+    };
+
+    path.with_extension("dart")
+}
+
+pub fn write_output(target: &Path, dart: &str) -> io::Result<()> {
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(target, dart)
+}
+
+#[cfg(test)]
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name)
+}
+
+#[cfg(test)]
+pub(super) fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("wdart-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+#[test]
+fn find_sources_walks_directories() {
+    let sources = find_sources(&fixture("imports")).unwrap();
+
+    let relative: Vec<_> = sources.iter().map(|source| &source.relative).collect();
+    assert_eq!(
+        relative,
+        vec![
+            Path::new("app.flutter"),
+            Path::new("conflict.flutter"),
+            Path::new("counter.flutter"),
+            Path::new("missing_import.flutter"),
+        ]
+    );
+}
+
+#[test]
+fn build_into_out_dir() {
+    let out_dir = temp_dir("build");
+    let args = Args {
+        command: Command::Build {
+            out_dir: Some(out_dir.clone()),
+        },
+        format: Format::Debug,
+        paths: vec![
+            fixture("imports/app.flutter"),
+            fixture("imports/counter.flutter"),
+        ],
+    };
+
+    assert_eq!(run(&args), EXIT_OK);
+    let dart = fs::read_to_string(out_dir.join("app.dart")).unwrap();
+    assert!(dart.contains("import './counter.dart';"), "{}", dart);
+
+    fs::remove_dir_all(out_dir).unwrap();
+}
+
+#[test]
+fn check_fails_on_a_syntax_error() {
+    let args = Args {
+        command: Command::Check,
+        format: Format::Debug,
+        paths: vec![fixture("cli/unclosed.flutter")],
+    };
+
+    assert_eq!(run(&args), EXIT_FAILED);
+}
+
+#[test]
+fn fmt_check_fails_on_unformatted_files_without_touching_them() {
+    let dir = temp_dir("fmt");
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("app.flutter");
+    fs::write(&path, "<Column>\n    <Text> 'a'\n").unwrap();
+    let args = |check| Args {
+        command: Command::Fmt { check },
+        format: Format::Debug,
+        paths: vec![path.clone()],
+    };
+
+    assert_eq!(run(&args(true)), EXIT_FAILED);
+    assert_eq!(run(&args(false)), EXIT_OK);
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "<Column>\n  <Text> \"a\"\n"
+    );
+    assert_eq!(run(&args(true)), EXIT_OK);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn check_fails_on_broken_imports() {
+    for name in ["missing_import.flutter", "conflict.flutter"] {
+        let args = Args {
+            command: Command::Check,
+            format: Format::Debug,
+            paths: vec![fixture("imports").join(name)],
+        };
+
+        assert_eq!(run(&args), EXIT_FAILED, "{}", name);
+    }
+}
+
+#[test]
+fn imported_widgets_are_checked() {
+    let dir = temp_dir("imported");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        dir.join("card.flutter"),
+        "widget Card(title: String)\n  <Self>\n    <Text> title\n",
+    )
+    .unwrap();
+    let path = dir.join("app.flutter");
+    let source = "import \"./card.flutter\";\n\n<Card>\n";
+    fs::write(&path, source).unwrap();
+
+    let file = path.display().to_string();
+    let diagnostics = compile(&file, source, &Config::default()).unwrap_err();
+    assert_eq!(
+        diagnostics[0].message,
+        "`Card` is missing the required argument `title`"
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn missing_input_fails() {
+    let args = Args {
+        command: Command::Check,
+        format: Format::Debug,
+        paths: vec![fixture("cli/does_not_exist.flutter")],
+    };
+
+    assert_eq!(run(&args), EXIT_FAILED);
+}
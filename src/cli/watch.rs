@@ -0,0 +1,406 @@
+use super::commands::{
+    compile, find_sources, output_path, parse_file, write_output, Source, EXIT_FAILED,
+};
+use crate::{
+    config::config::{Config, CONFIG_FILE},
+    guard_clause,
+    resolver::resolver::{resolve_import, Module, MARKUP_EXTENSION},
+};
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs, io,
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, Receiver, RecvTimeoutError},
+    time::Duration,
+};
+
+// editors write a file several times per save (temp file, rename, chmod...)
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// What the watcher knows about the tree it recompiles.
+#[derive(Debug, Default)]
+pub struct WatchState {
+    // canonical watched directories, to lay out `--out-dir` like `build` does
+    roots: Vec<PathBuf>,
+    // markup files each file imports, by canonical path
+    imports: HashMap<PathBuf, Vec<PathBuf>>,
+}
+
+impl WatchState {
+    /// The changed files plus the files importing them, directly or not, the only
+    /// ones whose output can differ after the change: a file is checked against
+    /// the declarations it imports, which may themselves come from imports. A
+    /// changed `wdart.toml` affects every file, its settings go into all of them.
+    pub fn affected(&self, changed: &BTreeSet<PathBuf>) -> BTreeSet<PathBuf> {
+        guard_clause!(changed.iter().any(|path| is_config(path)), self.sources());
+        let mut affected = changed.clone();
+
+        let mut pending: Vec<&PathBuf> = changed.iter().collect();
+        while let Some(path) = pending.pop() {
+            for (importer, imports) in &self.imports {
+                if imports.contains(path) && affected.insert(importer.clone()) {
+                    pending.push(importer);
+                }
+            }
+        }
+
+        affected
+    }
+
+    // every markup file under the watched directories
+    fn sources(&self) -> BTreeSet<PathBuf> {
+        self.roots
+            .iter()
+            .flat_map(|root| find_sources(root).unwrap_or_default())
+            .map(|source| source.path)
+            .collect()
+    }
+
+    // whether `path` is in what the user asked to watch
+    fn watches(&self, path: &Path) -> bool {
+        self.roots.iter().any(|root| path.starts_with(root))
+    }
+
+    fn source(&self, path: &Path) -> Source {
+        let relative = self
+            .roots
+            .iter()
+            .find_map(|root| match path == root {
+                // a file watched by itself goes by its name, like in `build`
+                true => path.file_name().map(Path::new),
+                false => path.strip_prefix(root).ok(),
+            })
+            .unwrap_or(path);
+
+        Source {
+            path: path.to_path_buf(),
+            relative: relative.to_path_buf(),
+        }
+    }
+
+    /// Compiles one file, printing its diagnostics instead of stopping on them,
+    /// or removes the output of a deleted one. Returns whether it compiled.
+    fn rebuild(&mut self, path: &Path, out_dir: Option<&Path>) -> bool {
+        let result = if path.exists() {
+            self.compile(path, out_dir)
+                .map(|target| format!("compiled {} -> {}", path.display(), target.display()))
+        } else {
+            self.remove(path, out_dir)
+                .map(|target| format!("removed {}", target.display()))
+        };
+
+        match result {
+            Ok(message) => {
+                println!("{}", message);
+                true
+            }
+            Err(message) => {
+                eprint!("{}", message);
+                false
+            }
+        }
+    }
+
+    fn compile(&mut self, path: &Path, out_dir: Option<&Path>) -> Result<PathBuf, String> {
+        let file = path.display().to_string();
+        let source = fs::read_to_string(path)
+            .map_err(|err| format!("error: cannot read {}: {}\n", file, err))?;
+        let config = Config::discover(path)?;
+        // tracked even when compiling fails, so that fixing an import retries it
+        self.track_imports(path, &source, &config);
+
+        let dart = compile(&file, &source, &config).map_err(|diagnostics| {
+            diagnostics
+                .iter()
+                .map(|diagnostic| diagnostic.render(&source))
+                .collect::<String>()
+        })?;
+        let target = self.target(path, out_dir, &config);
+        write_output(&target, &dart)
+            .map_err(|err| format!("error: cannot write {}: {}\n", target.display(), err))?;
+
+        Ok(target)
+    }
+
+    fn remove(&mut self, path: &Path, out_dir: Option<&Path>) -> Result<PathBuf, String> {
+        self.imports.remove(path);
+        let config = Config::discover(path)?;
+        let target = self.target(path, out_dir, &config);
+
+        match fs::remove_file(&target) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(format!(
+                "error: cannot remove {}: {}\n",
+                target.display(),
+                err
+            )),
+            _ => Ok(target),
+        }
+    }
+
+    // the imports of a file that doesn't parse are kept from its last version
+    fn track_imports(&mut self, path: &Path, source: &str, config: &Config) {
+        let (items, diagnostics) = parse_file(&path.display().to_string(), source, config);
+        if !diagnostics.is_empty() {
+            return;
+        }
+
+        let module = Module {
+            path: path.to_path_buf(),
+            source: source.to_string(),
+            items,
+        };
+        let imports = module
+            .imports()
+            .filter_map(|import| resolve_import(&module, import).ok().flatten())
+            .collect();
+        self.imports.insert(path.to_path_buf(), imports);
+    }
+
+    fn target(&self, path: &Path, out_dir: Option<&Path>, config: &Config) -> PathBuf {
+        let out_dir = out_dir.map(Path::to_path_buf).or_else(|| config.out_dir());
+        output_path(&self.source(path), out_dir.as_deref())
+    }
+}
+
+/// Compiles everything under `paths`, then recompiles whatever changes until
+/// the process is stopped.
+pub fn watch(paths: &[PathBuf], out_dir: Option<&Path>) -> u8 {
+    let mut state = WatchState::default();
+    let mut initial = BTreeSet::new();
+
+    for path in paths {
+        let sources = path.canonicalize().and_then(|root| {
+            let sources = find_sources(&root)?;
+            state.roots.push(root);
+            Ok(sources)
+        });
+        match sources {
+            Ok(sources) => initial.extend(sources.into_iter().map(|source| source.path)),
+            Err(err) => {
+                eprintln!("error: cannot read {}: {}", path.display(), err);
+                return EXIT_FAILED;
+            }
+        }
+    }
+
+    let (sender, events) = channel();
+    let mut watcher = match notify::recommended_watcher(sender) {
+        Ok(watcher) => watcher,
+        Err(err) => {
+            eprintln!("error: cannot watch for changes: {}", err);
+            return EXIT_FAILED;
+        }
+    };
+    for root in &state.roots {
+        if let Err(err) = watcher.watch(root, RecursiveMode::Recursive) {
+            eprintln!("error: cannot watch {}: {}", root.display(), err);
+            return EXIT_FAILED;
+        }
+    }
+    // The configuration may live above the watched directories. Its directory is
+    // watched rather than the file, which editors replace when saving.
+    let config_dirs: BTreeSet<PathBuf> = initial
+        .iter()
+        .filter_map(|path| Config::find(path))
+        .filter(|config| !state.watches(config))
+        .filter_map(|config| config.parent().map(Path::to_path_buf))
+        .collect();
+    for dir in &config_dirs {
+        if let Err(err) = watcher.watch(dir, RecursiveMode::NonRecursive) {
+            eprintln!("error: cannot watch {}: {}", dir.display(), err);
+            return EXIT_FAILED;
+        }
+    }
+
+    for path in &initial {
+        state.rebuild(path, out_dir);
+    }
+    println!("watching for changes...");
+
+    while let Some(mut changed) = next_changes(&events, DEBOUNCE) {
+        // only the configurations count in their directories
+        changed.retain(|path| is_config(path) || state.watches(path));
+        for path in state.affected(&changed) {
+            state.rebuild(&path, out_dir);
+        }
+    }
+
+    EXIT_FAILED
+}
+
+/// Blocks until some markup file or configuration changes, then keeps collecting
+/// changes until none came in for `quiet`. `None` once the watcher is gone.
+pub fn next_changes(
+    events: &Receiver<notify::Result<Event>>,
+    quiet: Duration,
+) -> Option<BTreeSet<PathBuf>> {
+    let mut changed = BTreeSet::new();
+
+    changed.extend(changed_inputs(events.recv().ok()?));
+    loop {
+        match events.recv_timeout(quiet) {
+            Ok(event) => changed.extend(changed_inputs(event)),
+            Err(RecvTimeoutError::Timeout) if !changed.is_empty() => return Some(changed),
+            Err(RecvTimeoutError::Timeout) => changed.extend(changed_inputs(events.recv().ok()?)),
+            Err(RecvTimeoutError::Disconnected) => return None,
+        }
+    }
+}
+
+fn changed_inputs(event: notify::Result<Event>) -> Vec<PathBuf> {
+    let event = match event {
+        Ok(event) => event,
+        Err(err) => {
+            eprintln!("warning: {}", err);
+            return Vec::new();
+        }
+    };
+
+    match event.kind {
+        EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_) => event
+            .paths
+            .into_iter()
+            .filter(|path| {
+                path.extension().and_then(|ext| ext.to_str()) == Some(MARKUP_EXTENSION)
+                    || is_config(path)
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn is_config(path: &Path) -> bool {
+    path.file_name().and_then(|name| name.to_str()) == Some(CONFIG_FILE)
+}
+
+#[cfg(test)]
+fn modified(path: &str) -> notify::Result<Event> {
+    let kind = EventKind::Modify(notify::event::ModifyKind::Any);
+    Ok(Event::new(kind).add_path(PathBuf::from(path)))
+}
+
+#[test]
+fn importers_of_a_changed_file_are_affected() {
+    let mut state = WatchState::default();
+    state.imports.insert(
+        PathBuf::from("/app.flutter"),
+        vec![PathBuf::from("/counter.flutter")],
+    );
+    state
+        .imports
+        .insert(PathBuf::from("/counter.flutter"), Vec::new());
+    state
+        .imports
+        .insert(PathBuf::from("/other.flutter"), Vec::new());
+
+    let changed = BTreeSet::from([PathBuf::from("/counter.flutter")]);
+    assert_eq!(
+        state.affected(&changed),
+        BTreeSet::from([
+            PathBuf::from("/app.flutter"),
+            PathBuf::from("/counter.flutter")
+        ])
+    );
+}
+
+#[test]
+fn importers_of_importers_are_affected() {
+    let mut state = WatchState::default();
+    state.imports.insert(
+        PathBuf::from("/app.flutter"),
+        vec![PathBuf::from("/page.flutter")],
+    );
+    state.imports.insert(
+        PathBuf::from("/page.flutter"),
+        vec![PathBuf::from("/card.flutter")],
+    );
+
+    let changed = BTreeSet::from([PathBuf::from("/card.flutter")]);
+    assert_eq!(
+        state.affected(&changed),
+        BTreeSet::from([
+            PathBuf::from("/app.flutter"),
+            PathBuf::from("/card.flutter"),
+            PathBuf::from("/page.flutter")
+        ])
+    );
+}
+
+#[test]
+fn a_changed_config_affects_every_file() {
+    let mut state = WatchState::default();
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/package");
+    state.roots.push(root.clone());
+
+    let changed = BTreeSet::from([root.join(CONFIG_FILE)]);
+    assert_eq!(
+        state.affected(&changed),
+        BTreeSet::from([
+            root.join("lib/cards/card.flutter"),
+            root.join("lib/home.flutter")
+        ])
+    );
+}
+
+#[test]
+fn files_watched_by_themselves_keep_their_name() {
+    let mut state = WatchState::default();
+    state.roots.push(PathBuf::from("/app.flutter"));
+    state.roots.push(PathBuf::from("/src"));
+
+    let target = |path: &str| output_path(&state.source(Path::new(path)), Some(Path::new("/gen")));
+    assert_eq!(target("/app.flutter"), PathBuf::from("/gen/app.dart"));
+    assert_eq!(
+        target("/src/pages/home.flutter"),
+        PathBuf::from("/gen/pages/home.dart")
+    );
+}
+
+#[test]
+fn save_bursts_are_debounced_into_one_change() {
+    let (sender, events) = channel();
+    sender.send(modified("/app.flutter")).unwrap();
+    sender.send(modified("/app.flutter~")).unwrap();
+    sender.send(modified("/app.flutter")).unwrap();
+    sender.send(modified("/counter.flutter")).unwrap();
+    sender.send(modified("/wdart.toml")).unwrap();
+
+    assert_eq!(
+        next_changes(&events, Duration::from_millis(10)),
+        Some(BTreeSet::from([
+            PathBuf::from("/app.flutter"),
+            PathBuf::from("/counter.flutter"),
+            PathBuf::from("/wdart.toml")
+        ]))
+    );
+
+    drop(sender);
+    assert_eq!(next_changes(&events, Duration::from_millis(10)), None);
+}
+
+#[test]
+fn failed_rebuild_keeps_the_state() {
+    let mut state = WatchState::default();
+    let broken = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/cli/unclosed.flutter");
+
+    assert!(!state.rebuild(&broken, None));
+    assert!(state.imports.is_empty());
+}
+
+#[test]
+fn deleted_sources_lose_their_output() {
+    let dir = super::commands::temp_dir("watch-delete");
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.canonicalize().unwrap().join("app.flutter");
+    fs::write(&path, "<Center>\n").unwrap();
+    let mut state = WatchState::default();
+
+    assert!(state.rebuild(&path, None));
+    assert!(path.with_extension("dart").exists());
+    fs::remove_file(&path).unwrap();
+    assert!(state.rebuild(&path, None));
+    assert!(!path.with_extension("dart").exists());
+
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,298 @@
+use crate::{
+    catalog::catalog::Catalog,
+    diagnostics::diagnostic::Diagnostic,
+    emitter::{
+        emitter::EmitOptions,
+        events::EventMap,
+        style::{color, Palette},
+    },
+    formatter::formatter::FormatOptions,
+    lexer::{lexer::LexOptions, token_struct::Span},
+};
+use serde::{de, Deserialize, Deserializer};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+pub const CONFIG_FILE: &str = "wdart.toml";
+
+// `import "package:my_app/cards.flutter";` reads `lib/cards.flutter` of the project
+const PACKAGE_SCHEME: &str = "package:";
+const PACKAGE_SOURCES: &str = "lib";
+
+/// The settings of a project, read from the `wdart.toml` in the directory of an
+/// input file or the closest of its parents. Everything is optional:
+///
+/// ```toml
+/// package = "my_app"
+/// indent-width = 2
+/// out-dir = "lib/generated"
+/// catalogs = ["widgets.json"]
+/// listenables = ["CounterPageController"]
+///
+/// [colors]
+/// brand = "#1e88e5"
+///
+/// [events]
+/// doubleTap = "onDoubleTap"
+///
+/// [widget-events.PrimaryButton]
+/// tap = "onPressed"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    // the Dart package the markup belongs to
+    pub package: Option<String>,
+    // spaces per level of markup indentation, checked by the lexer and laid out by
+    // `fmt`; any consistent width is accepted when unset
+    #[serde(deserialize_with = "indent_width")]
+    pub indent_width: Option<usize>,
+    // where `build` and `watch` write when `--out-dir` isn't given
+    pub out_dir: Option<PathBuf>,
+    // JSON widget catalogs adding to the Material one, or replacing its widgets
+    pub catalogs: Vec<PathBuf>,
+    // the project's classes that notify, like a `ChangeNotifier` subclass: widgets
+    // reading a param of one of them rebuild when it does
+    pub listenables: Vec<String>,
+    // style colors by name, each written like any style color: `#1e88e5`, `indigo-400`
+    #[serde(deserialize_with = "colors")]
+    pub colors: BTreeMap<String, String>,
+    // markup event => callback param, for every widget
+    pub events: BTreeMap<String, String>,
+    // widget => markup event => callback param
+    pub widget_events: BTreeMap<String, BTreeMap<String, String>>,
+
+    // the directory of the file, relative paths start from it
+    #[serde(skip)]
+    pub root: PathBuf,
+    // the Material catalog extended with `catalogs`
+    #[serde(skip)]
+    pub catalog: Catalog,
+}
+
+impl Config {
+    /// The configuration `file` is compiled with: the closest `wdart.toml` above
+    /// it, or the defaults when there is none. The error is ready to print.
+    pub fn discover(file: &Path) -> Result<Config, String> {
+        match Config::find(file) {
+            Some(path) => Config::load(&path),
+            None => Ok(Config::default()),
+        }
+    }
+
+    /// The closest `wdart.toml` above `file`.
+    pub fn find(file: &Path) -> Option<PathBuf> {
+        let file = file.canonicalize().unwrap_or_else(|_| file.to_path_buf());
+
+        file.ancestors()
+            .skip(1)
+            .map(|dir| dir.join(CONFIG_FILE))
+            .find(|path| path.is_file())
+    }
+
+    pub fn load(path: &Path) -> Result<Config, String> {
+        let file = path.display().to_string();
+        let source = fs::read_to_string(path)
+            .map_err(|err| format!("error: cannot read {}: {}\n", file, err))?;
+
+        let mut config =
+            Config::from_toml(&file, &source).map_err(|diagnostic| diagnostic.render(&source))?;
+        config.root = path.parent().unwrap_or(Path::new(".")).to_path_buf();
+        config.load_catalogs()?;
+        Ok(config)
+    }
+
+    /// Reads the settings themselves, leaving `root` and the catalogs to `load`.
+    /// Errors point at the key or value at fault.
+    pub fn from_toml(file: &str, source: &str) -> Result<Config, Diagnostic> {
+        toml::from_str(source).map_err(|err| {
+            let span = err
+                .span()
+                .map_or_else(Span::default, |span| Span::new(span.start, span.end));
+            Diagnostic::error(file, span, err.message())
+        })
+    }
+
+    fn load_catalogs(&mut self) -> Result<(), String> {
+        for path in &self.catalogs {
+            let path = self.root.join(path);
+            let json = fs::read_to_string(&path).map_err(|err| {
+                format!("error: cannot read catalog {}: {}\n", path.display(), err)
+            })?;
+            let catalog = Catalog::from_json(&json)
+                .map_err(|err| format!("error: invalid catalog {}: {}\n", path.display(), err))?;
+            self.catalog.extend(catalog);
+        }
+        Ok(())
+    }
+
+    pub fn lex_options(&self) -> LexOptions {
+        LexOptions {
+            indent_width: self.indent_width,
+        }
+    }
+
+    pub fn format_options(&self) -> FormatOptions {
+        match self.indent_width {
+            Some(indent_width) => FormatOptions { indent_width },
+            None => FormatOptions::default(),
+        }
+    }
+
+    pub fn emit_options(&self) -> EmitOptions {
+        let mut events = EventMap::default();
+        for (event, param) in &self.events {
+            events.insert(event, param);
+        }
+        for (widget, overrides) in &self.widget_events {
+            for (event, param) in overrides {
+                events.insert_for(widget, event, param);
+            }
+        }
+
+        let palette: Palette = self
+            .colors
+            .iter()
+            .filter_map(|(name, value)| Some((name.clone(), color(value)?)))
+            .collect();
+
+        EmitOptions {
+            events,
+            catalog: self.catalog.clone(),
+            palette,
+            listenables: self.listenables.clone(),
+        }
+    }
+
+    pub fn out_dir(&self) -> Option<PathBuf> {
+        self.out_dir.as_ref().map(|dir| self.root.join(dir))
+    }
+
+    /// Where a `package:` import of this project's own package points to, `None`
+    /// for relative imports and for other packages.
+    pub fn package_path(&self, import: &str) -> Option<PathBuf> {
+        let package = self.package.as_deref()?;
+        let path = import
+            .strip_prefix(PACKAGE_SCHEME)?
+            .strip_prefix(package)?
+            .strip_prefix('/')?;
+
+        Some(self.root.join(PACKAGE_SOURCES).join(path))
+    }
+}
+
+fn indent_width<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<usize>, D::Error> {
+    match usize::deserialize(deserializer)? {
+        0 => Err(de::Error::custom("`indent-width` must be at least 1")),
+        width => Ok(Some(width)),
+    }
+}
+
+// checked one by one, so that an error points at the color at fault
+fn colors<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<BTreeMap<String, String>, D::Error> {
+    let colors = BTreeMap::<String, StyleColor>::deserialize(deserializer)?;
+    Ok(colors
+        .into_iter()
+        .map(|(name, StyleColor(value))| (name, value))
+        .collect())
+}
+
+struct StyleColor(String);
+
+impl<'de> Deserialize<'de> for StyleColor {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        match color(&value) {
+            Some(_) => Ok(StyleColor(value)),
+            None => Err(de::Error::custom(format!(
+                "`{}` is not a color, colors are written like in styles: `#1e88e5`, `indigo-400` or `white`",
+                value
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+fn config(source: &str) -> Result<Config, Diagnostic> {
+    Config::from_toml(CONFIG_FILE, source)
+}
+
+#[test]
+fn settings_drive_the_options() {
+    let config = config(concat!(
+        "package = \"my_app\"\n",
+        "indent-width = 4\n",
+        "listenables = [\"CounterPageController\"]\n",
+        "\n",
+        "[colors]\n",
+        "brand = \"#1e88e5\"\n",
+        "\n",
+        "[widget-events.PrimaryButton]\n",
+        "tap = \"onPressed\"\n",
+    ))
+    .unwrap();
+
+    assert_eq!(config.format_options().indent_width, 4);
+    assert_eq!(config.lex_options().indent_width, Some(4));
+    let options = config.emit_options();
+    assert_eq!(options.palette["brand"], "Color(0xFF1E88E5)");
+    assert_eq!(options.listenables, vec!["CounterPageController"]);
+    assert_eq!(
+        options.events.resolve("PrimaryButton", "tap"),
+        Some("onPressed")
+    );
+    assert_eq!(options.events.resolve("InkWell", "tap"), Some("onTap"));
+    assert_eq!(
+        config.package_path("package:my_app/cards/card.flutter"),
+        Some(PathBuf::from("lib/cards/card.flutter"))
+    );
+    assert_eq!(config.package_path("package:other/card.flutter"), None);
+}
+
+#[test]
+fn unknown_keys_are_reported_where_they_are() {
+    let err = config("indent-width = 2\n\n[colors]\nbrand = \"red\"\n\n[colours]\n").unwrap_err();
+
+    assert_eq!(err.span, Span::new(43, 50));
+    assert!(
+        err.message.starts_with("unknown field `colours`"),
+        "{}",
+        err.message
+    );
+
+    let err = config("[widget-events.Button]\ntap = 1\n").unwrap_err();
+    assert_eq!(err.span, Span::new(29, 30));
+}
+
+#[test]
+fn invalid_colors_are_errors() {
+    let err = config("[colors]\nbrand = \"not a color\"").unwrap_err();
+
+    assert_eq!(err.span, Span::new(17, 30));
+}
+
+#[test]
+fn zero_indent_width_is_an_error() {
+    let err = config("package = \"app\"\nindent-width = 0\n").unwrap_err();
+
+    assert_eq!(err.span, Span::new(31, 32));
+    assert!(err.message.contains("at least 1"), "{}", err.message);
+}
+
+#[test]
+fn discover_searches_the_parent_directories() {
+    let dir = std::env::temp_dir().join(format!("wdart-config-{}", std::process::id()));
+    let nested = dir.join("lib/pages");
+    fs::create_dir_all(&nested).unwrap();
+    fs::write(dir.join(CONFIG_FILE), "out-dir = \"build\"\n").unwrap();
+
+    let config = Config::discover(&nested.join("home.flutter")).unwrap();
+    assert_eq!(config.out_dir(), Some(dir.join("build")));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,221 @@
+use crate::lexer::token_struct::Span;
+use codemap::CodeMap;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl Severity {
+    fn describe(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Note => "note",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+/// A message about a span of a source file, rendered the way rustc does:
+///
+/// ```text
+/// error: expected `>`, found end of file
+///  --> app.flutter:1:8
+///   |
+/// 1 | <Button
+///   |        ^ expected `>` here
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub file: String,
+    pub span: Span,
+    pub severity: Severity,
+    pub message: String,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, file: &str, span: Span, message: &str) -> Self {
+        Diagnostic {
+            file: file.to_string(),
+            span,
+            severity,
+            message: message.to_string(),
+            labels: Vec::new(),
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn error(file: &str, span: Span, message: &str) -> Self {
+        Diagnostic::new(Severity::Error, file, span, message)
+    }
+
+    pub fn warning(file: &str, span: Span, message: &str) -> Self {
+        Diagnostic::new(Severity::Warning, file, span, message)
+    }
+
+    pub fn with_label(mut self, span: Span, message: &str) -> Self {
+        self.labels.push(Label {
+            span,
+            message: message.to_string(),
+        });
+        self
+    }
+
+    pub fn with_note(mut self, note: &str) -> Self {
+        self.notes.push(note.to_string());
+        self
+    }
+
+    /// Renders the diagnostic against `source`, the contents of `self.file`.
+    pub fn render(&self, source: &str) -> String {
+        let mut codemap = CodeMap::new();
+        let file = codemap.add_file(self.file.clone(), source.to_string());
+        let clamp = |span: Span| {
+            let end = span.end.min(source.len());
+            file.span.subspan(span.start.min(end) as u64, end as u64)
+        };
+
+        let primary = codemap.look_up_span(clamp(self.span));
+        // the primary span underlines with ^, the other labels with -
+        let mut marks = vec![(self.span, '^', self.primary_message())];
+        for label in &self.labels {
+            if label.span != self.span {
+                marks.push((label.span, '-', Some(label.message.as_str())));
+            }
+        }
+
+        let lines: Vec<_> = marks
+            .iter()
+            .map(|(span, ..)| codemap.look_up_span(clamp(*span)).begin.line)
+            .collect();
+        let gutter = (lines.iter().max().unwrap_or(&0) + 1).to_string().len();
+        let pad = " ".repeat(gutter);
+
+        let mut out = String::new();
+        let _ = writeln!(out, "{}: {}", self.severity.describe(), self.message);
+        let _ = writeln!(
+            out,
+            "{}--> {}:{}:{}",
+            pad,
+            self.file,
+            primary.begin.line + 1,
+            primary.begin.column + 1
+        );
+        let _ = writeln!(out, "{} |", pad);
+
+        let mut sorted: Vec<_> = lines.iter().zip(&marks).collect();
+        sorted.sort_by_key(|(line, (span, ..))| (**line, span.start));
+
+        let mut last_line = None;
+        for (line, (span, mark, message)) in sorted {
+            if last_line != Some(*line) {
+                let text = file.source_line(*line).trim_end_matches('\r');
+                let _ = writeln!(out, "{:>width$} | {}", line + 1, text, width = gutter);
+                last_line = Some(*line);
+            }
+
+            let location = codemap.look_up_span(clamp(*span));
+            let start = location.begin.column;
+            // a span running past its first line is underlined up to the line end
+            let end = if location.end.line == *line {
+                location.end.column
+            } else {
+                file.source_line(*line).chars().count()
+            };
+            let underline = mark.to_string().repeat((end.max(start + 1)) - start);
+            let message = message.map(|m| format!(" {}", m)).unwrap_or_default();
+            let _ = writeln!(
+                out,
+                "{} | {}{}{}",
+                pad,
+                " ".repeat(start),
+                underline,
+                message
+            );
+        }
+
+        for note in &self.notes {
+            let _ = writeln!(out, "{} = note: {}", pad, note);
+        }
+
+        out
+    }
+
+    // the label sharing the primary span is printed next to its carets
+    fn primary_message(&self) -> Option<&str> {
+        self.labels
+            .iter()
+            .find(|label| label.span == self.span)
+            .map(|label| label.message.as_str())
+    }
+}
+
+#[test]
+fn render_points_carets_at_the_primary_span() {
+    let source = "<Column>\n  <Text \"hi\">\n";
+    let diagnostic = Diagnostic::error("app.flutter", Span::new(17, 21), "expected `>`")
+        .with_label(Span::new(17, 21), "expected `>` here");
+
+    assert_eq!(
+        diagnostic.render(source),
+        concat!(
+            "error: expected `>`\n",
+            " --> app.flutter:2:9\n",
+            "  |\n",
+            "2 |   <Text \"hi\">\n",
+            "  |         ^^^^ expected `>` here\n",
+        )
+    );
+}
+
+#[test]
+fn render_secondary_labels_and_notes() {
+    let source = "widget Card(title: String)\n  <Text>title\n  <Text>titel\n";
+    let diagnostic = Diagnostic::error("card.flutter", Span::new(49, 54), "unknown name `titel`")
+        .with_label(Span::new(12, 17), "did you mean this parameter?")
+        .with_note("parameters are bound by name");
+
+    assert_eq!(
+        diagnostic.render(source),
+        concat!(
+            "error: unknown name `titel`\n",
+            " --> card.flutter:3:9\n",
+            "  |\n",
+            "1 | widget Card(title: String)\n",
+            "  |             ----- did you mean this parameter?\n",
+            "3 |   <Text>titel\n",
+            "  |         ^^^^^\n",
+            "  = note: parameters are bound by name\n",
+        )
+    );
+}
+
+#[test]
+fn render_span_at_end_of_file() {
+    let source = "<Button";
+    let diagnostic = Diagnostic::error("app.flutter", Span::new(7, 7), "expected `>`");
+
+    assert_eq!(
+        diagnostic.render(source),
+        concat!(
+            "error: expected `>`\n",
+            " --> app.flutter:1:8\n",
+            "  |\n",
+            "1 | <Button\n",
+            "  |        ^\n",
+        )
+    );
+}
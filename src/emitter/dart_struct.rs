@@ -0,0 +1,567 @@
+use crate::guard_clause;
+
+const INDENT: &str = "  ";
+
+// like `dart format`, calls only stay on one line when they fit
+const MAX_WIDTH: usize = 80;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DartExpr {
+    // Widget(positional, child: ..., children: [...])
+    Call {
+        callee: String,
+        positional: Vec<DartExpr>,
+        named: Vec<(String, DartExpr)>,
+    },
+    // [a, b, c]
+    List(Vec<DartExpr>),
+    // Colors.yellow.shade100, 10, Alignment.center
+    Raw(String),
+    // collection-if, only valid as a list element: `if (a) A() else if (b) B() else C()`,
+    // with `...[...]` spreads when a branch has several elements
+    If {
+        // the condition of each branch, `None` for the final `else`
+        branches: Vec<(Option<String>, Vec<DartExpr>)>,
+    },
+    // collection-for, only valid as a list element: `for (final item in items) A()`
+    For {
+        item: String,
+        index: Option<String>,
+        // safe to follow with `.indexed`
+        iterable: String,
+        elements: Vec<DartExpr>,
+    },
+    // (context, index) { final item = items[index]; return A(); }
+    Closure {
+        params: Vec<String>,
+        statements: Vec<String>,
+        result: Box<DartExpr>,
+    },
+    // a ? A() : B()
+    Ternary {
+        condition: String,
+        then: Box<DartExpr>,
+        otherwise: Box<DartExpr>,
+    },
+    // an expression with the markup comments written above it, one line each
+    Commented {
+        comments: Vec<String>,
+        expr: Box<DartExpr>,
+    },
+}
+
+impl DartExpr {
+    pub fn call(callee: &str) -> Self {
+        DartExpr::Call {
+            callee: callee.to_string(),
+            positional: Vec::new(),
+            named: Vec::new(),
+        }
+    }
+
+    pub fn raw(code: &str) -> Self {
+        DartExpr::Raw(code.to_string())
+    }
+
+    pub fn with_positional(mut self, value: DartExpr) -> Self {
+        if let DartExpr::Call { positional, .. } = &mut self {
+            positional.push(value);
+        }
+        self
+    }
+
+    // ListView => ListView.builder
+    pub fn with_callee(mut self, name: &str) -> Self {
+        if let DartExpr::Call { callee, .. } = &mut self {
+            *callee = name.to_string();
+        }
+        self
+    }
+
+    pub fn with_named(mut self, name: &str, value: DartExpr) -> Self {
+        if let DartExpr::Call { named, .. } = &mut self {
+            named.push((name.to_string(), value));
+        }
+        self
+    }
+
+    // the comments go before the ones the expression already has
+    pub fn with_comments(self, mut comments: Vec<String>) -> Self {
+        guard_clause!(comments.is_empty(), self);
+        match self {
+            DartExpr::Commented {
+                comments: inner,
+                expr,
+            } => {
+                comments.extend(inner);
+                DartExpr::Commented { comments, expr }
+            }
+            _ => DartExpr::Commented {
+                comments,
+                expr: Box::new(self),
+            },
+        }
+    }
+
+    /// A conditional where a single widget goes: `if`/`else if`/`else` become nested
+    /// ternaries, and `otherwise` is shown when there is no `else`. `None` when a
+    /// branch doesn't hold exactly one element. Anything else is returned as is.
+    pub fn into_ternary(self, otherwise: DartExpr) -> Option<DartExpr> {
+        match self {
+            DartExpr::If { branches } => {
+                let mut result = otherwise;
+                for (condition, mut elements) in branches.into_iter().rev() {
+                    guard_clause!(elements.len() != 1, None);
+                    let element = elements.remove(0);
+                    result = match condition {
+                        Some(condition) => DartExpr::Ternary {
+                            condition,
+                            then: Box::new(element),
+                            otherwise: Box::new(result),
+                        },
+                        None => element,
+                    };
+                }
+                Some(result)
+            }
+            DartExpr::Commented { comments, expr } => {
+                Some(expr.into_ternary(otherwise)?.with_comments(comments))
+            }
+            _ => Some(self),
+        }
+    }
+
+    pub fn has_named(&self, name: &str) -> bool {
+        match self {
+            DartExpr::Call { named, .. } => named.iter().any(|(existing, _)| existing == name),
+            _ => false,
+        }
+    }
+
+    // Calls whose arguments are all plain values stay on one line: `EdgeInsets.all(10)`
+    fn is_inline(&self) -> bool {
+        match self {
+            DartExpr::Call {
+                positional, named, ..
+            } => positional
+                .iter()
+                .chain(named.iter().map(|(_, value)| value))
+                .all(|arg| matches!(arg, DartExpr::Raw(_))),
+            DartExpr::List(items) => items.is_empty(),
+            DartExpr::If { branches } => branches.iter().all(
+                |(_, elements)| matches!(elements.as_slice(), [element] if element.is_inline()),
+            ),
+            DartExpr::For { elements, .. } => {
+                matches!(elements.as_slice(), [element] if element.is_inline())
+            }
+            DartExpr::Closure {
+                statements, result, ..
+            } => statements.is_empty() && result.is_inline(),
+            DartExpr::Ternary {
+                then, otherwise, ..
+            } => then.is_inline() && otherwise.is_inline(),
+            DartExpr::Raw(_) => true,
+            DartExpr::Commented { .. } => false,
+        }
+    }
+
+    // for (final item in items), for (final (index, item) in items.indexed)
+    fn for_header(item: &str, index: &Option<String>, iterable: &str) -> String {
+        match index {
+            Some(index) => format!("for (final ({}, {}) in {}.indexed)", index, item, iterable),
+            None => format!("for (final {} in {})", item, iterable),
+        }
+    }
+
+    // collection-if needs a spread as soon as one branch has several elements
+    fn has_spread(branches: &[(Option<String>, Vec<DartExpr>)]) -> bool {
+        branches.iter().any(|(_, elements)| elements.len() != 1)
+    }
+
+    fn render_inline(&self) -> String {
+        match self {
+            DartExpr::Call {
+                callee,
+                positional,
+                named,
+            } => {
+                let args: Vec<String> = positional
+                    .iter()
+                    .map(|value| value.render_inline())
+                    .chain(
+                        named
+                            .iter()
+                            .map(|(name, value)| format!("{}: {}", name, value.render_inline())),
+                    )
+                    .collect();
+                format!("{}({})", callee, args.join(", "))
+            }
+            DartExpr::List(_) => String::from("[]"),
+            DartExpr::If { branches } => {
+                let branches: Vec<String> = branches
+                    .iter()
+                    .map(|(condition, elements)| {
+                        let elements: Vec<String> =
+                            elements.iter().map(DartExpr::render_inline).collect();
+                        match condition {
+                            Some(condition) => format!("if ({}) {}", condition, elements.concat()),
+                            None => elements.concat(),
+                        }
+                    })
+                    .collect();
+                branches.join(" else ")
+            }
+            DartExpr::For {
+                item,
+                index,
+                iterable,
+                elements,
+            } => {
+                let elements: Vec<String> = elements.iter().map(DartExpr::render_inline).collect();
+                format!(
+                    "{} {}",
+                    DartExpr::for_header(item, index, iterable),
+                    elements.concat()
+                )
+            }
+            DartExpr::Closure { params, result, .. } => {
+                format!("({}) => {}", params.join(", "), result.render_inline())
+            }
+            DartExpr::Ternary {
+                condition,
+                then,
+                otherwise,
+            } => format!(
+                "{} ? {} : {}",
+                condition,
+                then.render_inline(),
+                otherwise.render_inline()
+            ),
+            DartExpr::Raw(code) => code.clone(),
+            DartExpr::Commented { expr, .. } => expr.render_inline(),
+        }
+    }
+
+    /// The comment lines to write before the line holding the expression, each
+    /// indented with `pad`, and the expression itself.
+    pub fn split_comments(&self, pad: &str) -> (String, &DartExpr) {
+        match self {
+            DartExpr::Commented { comments, expr } => {
+                let lines = comments
+                    .iter()
+                    .map(|comment| format!("{}{}\n", pad, comment))
+                    .collect();
+                (lines, expr)
+            }
+            _ => (String::new(), self),
+        }
+    }
+
+    /// Renders the expression the way `dart format` would lay out a widget
+    /// tree: one argument per line, trailing commas everywhere.
+    pub fn render(&self, depth: usize) -> String {
+        let pad = INDENT.repeat(depth + 1);
+        let close_pad = INDENT.repeat(depth);
+
+        match self {
+            DartExpr::Call {
+                callee,
+                positional,
+                named,
+            } if self.is_inline() && self.render_inline().len() + close_pad.len() <= MAX_WIDTH => {
+                self.render_inline()
+            }
+            DartExpr::Call {
+                callee,
+                positional,
+                named,
+            } => {
+                let mut out = format!("{}(\n", callee);
+                for value in positional {
+                    let (comments, value) = value.split_comments(&pad);
+                    out += &format!("{}{}{},\n", comments, pad, value.render(depth + 1));
+                }
+                for (name, value) in named {
+                    let (comments, value) = value.split_comments(&pad);
+                    out += &format!(
+                        "{}{}{}: {},\n",
+                        comments,
+                        pad,
+                        name,
+                        value.render(depth + 1)
+                    );
+                }
+                out + &close_pad + ")"
+            }
+            DartExpr::List(items) if items.is_empty() => String::from("[]"),
+            DartExpr::List(items) => {
+                let mut out = String::from("[\n");
+                for item in items {
+                    let (comments, item) = item.split_comments(&pad);
+                    out += &format!("{}{}{},\n", comments, pad, item.render(depth + 1));
+                }
+                out + &close_pad + "]"
+            }
+            DartExpr::If { branches } if DartExpr::has_spread(branches) => {
+                let branches: Vec<String> = branches
+                    .iter()
+                    .map(|(condition, elements)| {
+                        let spread = DartExpr::List(elements.clone()).render(depth);
+                        match condition {
+                            Some(condition) => format!("if ({}) ...{}", condition, spread),
+                            None => format!("...{}", spread),
+                        }
+                    })
+                    .collect();
+                branches.join(" else ")
+            }
+            DartExpr::For {
+                item,
+                index,
+                iterable,
+                elements,
+            } if elements.len() != 1 => format!(
+                "{} ...{}",
+                DartExpr::for_header(item, index, iterable),
+                DartExpr::List(elements.clone()).render(depth)
+            ),
+            DartExpr::If { .. } | DartExpr::For { .. } | DartExpr::Ternary { .. }
+                if self.is_inline()
+                    && self.render_inline().len() + close_pad.len() <= MAX_WIDTH =>
+            {
+                self.render_inline()
+            }
+            DartExpr::If { branches } => {
+                let mut out = String::new();
+                for (index, (condition, elements)) in branches.iter().enumerate() {
+                    out += &match (index, condition) {
+                        (0, Some(condition)) => format!("if ({})", condition),
+                        (_, Some(condition)) => format!("\n{}else if ({})", close_pad, condition),
+                        (_, None) => format!("\n{}else", close_pad),
+                    };
+                    let (comments, element) = elements[0].split_comments(&pad);
+                    out += &format!("\n{}{}{}", comments, pad, element.render(depth + 1));
+                }
+                out
+            }
+            DartExpr::For {
+                item,
+                index,
+                iterable,
+                elements,
+            } => {
+                let (comments, element) = elements[0].split_comments(&pad);
+                format!(
+                    "{}\n{}{}{}",
+                    DartExpr::for_header(item, index, iterable),
+                    comments,
+                    pad,
+                    element.render(depth + 1)
+                )
+            }
+            // a body without comments or statements fits an arrow function
+            DartExpr::Closure {
+                params,
+                statements,
+                result,
+            } if statements.is_empty() && !matches!(**result, DartExpr::Commented { .. }) => {
+                format!("({}) => {}", params.join(", "), result.render(depth))
+            }
+            DartExpr::Closure {
+                params,
+                statements,
+                result,
+            } => {
+                let mut out = format!("({}) {{\n", params.join(", "));
+                for statement in statements {
+                    out += &format!("{}{}\n", pad, statement);
+                }
+                let (comments, result) = result.split_comments(&pad);
+                out += &format!("{}{}return {};\n", comments, pad, result.render(depth + 1));
+                out + &close_pad + "}"
+            }
+            // like `dart format`, the branches go on lines of their own, 4 spaces in
+            DartExpr::Ternary {
+                condition,
+                then,
+                otherwise,
+            } => {
+                let branch_pad = INDENT.repeat(depth + 2);
+                let (then_comments, then) = then.split_comments(&branch_pad);
+                let (otherwise_comments, otherwise) = otherwise.split_comments(&branch_pad);
+                format!(
+                    "{}\n{}{}? {}\n{}{}: {}",
+                    condition,
+                    then_comments,
+                    branch_pad,
+                    then.render(depth + 3),
+                    otherwise_comments,
+                    branch_pad,
+                    otherwise.render(depth + 3)
+                )
+            }
+            DartExpr::Raw(code) => code.clone(),
+            // the comments only have a line of their own where the caller split them off
+            DartExpr::Commented { expr, .. } => expr.render(depth),
+        }
+    }
+}
+
+#[test]
+fn render_call_without_arguments() {
+    let expr = DartExpr::call("Text");
+
+    assert_eq!(expr.render(0), "Text()");
+}
+
+#[test]
+fn render_call_with_plain_values_inline() {
+    let expr = DartExpr::call("EdgeInsets.symmetric")
+        .with_named("horizontal", DartExpr::raw("8"))
+        .with_named("vertical", DartExpr::raw("4"));
+
+    assert_eq!(
+        expr.render(3),
+        "EdgeInsets.symmetric(horizontal: 8, vertical: 4)"
+    );
+}
+
+#[test]
+fn render_long_call_on_several_lines() {
+    let expr = DartExpr::call("Icon")
+        .with_named("semanticLabel", DartExpr::raw("\"A label long enough\""))
+        .with_named("size", DartExpr::raw("controller.iconSize"))
+        .with_named("color", DartExpr::raw("Colors.red"));
+    let should_be = "\
+    Icon(
+      semanticLabel: \"A label long enough\",
+      size: controller.iconSize,
+      color: Colors.red,
+    )";
+
+    pretty_assertions::assert_eq!(expr.render(2), should_be);
+}
+
+#[test]
+fn render_nested_calls_with_trailing_commas() {
+    let expr = DartExpr::call("Center").with_named(
+        "child",
+        DartExpr::call("Column").with_named(
+            "children",
+            DartExpr::List(vec![DartExpr::call("Text"), DartExpr::call("Icon")]),
+        ),
+    );
+    let should_be = "\
+Center(
+  child: Column(
+    children: [
+      Text(),
+      Icon(),
+    ],
+  ),
+)";
+
+    pretty_assertions::assert_eq!(expr.render(0), should_be);
+}
+
+#[test]
+fn render_comments_on_the_lines_before_the_argument() {
+    let expr = DartExpr::call("Center").with_named(
+        "child",
+        DartExpr::call("Text").with_comments(vec![String::from("// greeting")]),
+    );
+    let should_be = "\
+Center(
+  // greeting
+  child: Text(),
+)";
+
+    pretty_assertions::assert_eq!(expr.render(0), should_be);
+}
+
+#[test]
+fn render_short_collection_if_inline() {
+    let expr = DartExpr::List(vec![DartExpr::If {
+        branches: vec![
+            (Some(String::from("a")), vec![DartExpr::call("Text")]),
+            (None, vec![DartExpr::call("Icon")]),
+        ],
+    }]);
+
+    pretty_assertions::assert_eq!(expr.render(0), "[\n  if (a) Text() else Icon(),\n]");
+}
+
+#[test]
+fn render_collection_if_with_several_elements_as_spreads() {
+    let expr = DartExpr::List(vec![DartExpr::If {
+        branches: vec![
+            (
+                Some(String::from("a")),
+                vec![DartExpr::call("Text"), DartExpr::call("Icon")],
+            ),
+            (Some(String::from("b")), vec![DartExpr::call("Divider")]),
+        ],
+    }]);
+    let should_be = "\
+[
+  if (a) ...[
+    Text(),
+    Icon(),
+  ] else if (b) ...[
+    Divider(),
+  ],
+]";
+
+    pretty_assertions::assert_eq!(expr.render(0), should_be);
+}
+
+#[test]
+fn render_long_ternary_on_several_lines() {
+    let long = DartExpr::call("Text").with_positional(DartExpr::raw(
+        "\"a string long enough to push the call well past the width limit\"",
+    ));
+    let expr = DartExpr::If {
+        branches: vec![(Some(String::from("controller.loading")), vec![long])],
+    }
+    .into_ternary(DartExpr::raw("null"))
+    .unwrap();
+    let should_be = "\
+controller.loading
+        ? Text(
+            \"a string long enough to push the call well past the width limit\",
+          )
+        : null";
+
+    pretty_assertions::assert_eq!(expr.render(2), should_be);
+}
+
+#[test]
+fn render_collection_for_with_an_index() {
+    let expr = DartExpr::List(vec![DartExpr::For {
+        item: String::from("entry"),
+        index: Some(String::from("index")),
+        iterable: String::from("controller.history"),
+        elements: vec![DartExpr::call("Text").with_positional(DartExpr::raw("entry"))],
+    }]);
+
+    pretty_assertions::assert_eq!(
+        expr.render(0),
+        "[\n  for (final (index, entry) in controller.history.indexed) Text(entry),\n]"
+    );
+}
+
+#[test]
+fn render_builder_closure_with_statements() {
+    let expr = DartExpr::Closure {
+        params: vec![String::from("context"), String::from("index")],
+        statements: vec![String::from("final entry = history[index];")],
+        result: Box::new(DartExpr::call("Text").with_positional(DartExpr::raw("entry"))),
+    };
+    let should_be = "\
+(context, index) {
+  final entry = history[index];
+  return Text(entry);
+}";
+
+    pretty_assertions::assert_eq!(expr.render(0), should_be);
+}
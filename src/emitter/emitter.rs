@@ -0,0 +1,1389 @@
+use super::{
+    dart_struct::DartExpr,
+    events::EventMap,
+    expression::{dart_string, emit_expr},
+    listenable::{
+        block_reads, expr_reads, is_listenable, listen, listens, widget_reads, LISTEN_ATTRIBUTE,
+    },
+    scope::Scope,
+    style::{apply_style, Palette},
+};
+use crate::{
+    catalog::catalog::{Catalog, ChildArity, WidgetSpec},
+    diagnostics::diagnostic::Diagnostic,
+    golden_test, guard_clause,
+    parser::ast_struct::{
+        Comment, CommentKind, Expr, ExprKind, ForNode, IfNode, Import, Item, Node, Span, Widget,
+        WidgetDecl,
+    },
+    resolver::resolver::MARKUP_EXTENSION,
+};
+use std::path::Path;
+
+// Bare strings between widgets are shown with an implicit `Text(...)`.
+const TEXT_WIDGET: &str = "Text";
+
+// Every widget constructor takes a `key:`, the catalog doesn't repeat it.
+const KEY_ARGUMENT: &str = "key";
+
+// `<Self>` stands for the declared widget itself, its children are what `build()` returns.
+pub(crate) const SELF_WIDGET: &str = "Self";
+
+// `<AppBar slot="appBar">` is passed to its parent as `appBar:` instead of `child:`.
+pub(crate) const SLOT_ATTRIBUTE: &str = "slot";
+
+// `<Builder slot="builder" itemCount:n>` under a widget with a lazy constructor
+// builds its only child for each index: `ListView.builder(itemCount: n, ...)`.
+const BUILDER_SLOT: &str = "builder";
+const BUILDER_WIDGET: &str = "Builder";
+const ITEM_COUNT_ARGUMENT: &str = "itemCount";
+const ITEM_BUILDER_ARGUMENT: &str = "itemBuilder";
+const INDEX_PARAMETER: &str = "index";
+
+// What an `<if>` without `<else>` shows where a widget is required.
+const EMPTY_WIDGET: &str = "const SizedBox.shrink()";
+
+// What a handler like `@change:query = value` assigns, for callbacks given a value.
+const VALUE_PARAMETER: &str = "value";
+
+const FLUTTER_IMPORT: &str = "package:flutter/material.dart";
+
+#[derive(Debug)]
+pub enum EmitError {
+    // where the widgets would go: the file or the declaration
+    EmptyProgram(Span),
+    // the roots after the first one, and how many there are in all
+    MultipleRoots(Span, usize),
+    UnknownStyle(Span, String),
+    InvalidStyleValue(Span, String, String),
+    // a style key given twice, with where it was given first
+    DuplicateStyle(Span, String, Span),
+    UnknownEvent(Span, String, String),
+    DuplicateSlot(Span, String, String),
+    UnboundIdentifier(Span, String),
+    DuplicateArgument(Span, String, String),
+    // checked against the widget catalog
+    UnknownArgument(Span, String, String),
+    MissingArgument(Span, String, String),
+    UnexpectedContent(Span, String),
+    // the widget, what it takes, how many it was given and its slots
+    ChildCount(Span, String, ChildArity, usize, Vec<String>),
+    // `<if>`, `<else if>`, `<else>` or `<for>` without children
+    EmptyBlock(Span),
+    // a conditional passed as a single child, with a branch of several widgets
+    BranchChildCount(Span),
+    // a slotted widget under `<if>` or `<for>`, which is named
+    SlotInBlock(Span, String),
+    // a loop passed where a widget takes a single child
+    SingleChildLoop(Span),
+    // a `key:` on a loop whose children aren't a single widget
+    KeyedLoopChildren(Span),
+    // a handler assigning to something other than a `state`, named if it's a name
+    AssignToNonState(Span, Option<String>),
+    // a `state` named like a param or another state of the same widget
+    DuplicateState(Span, String),
+    // `listen="..."` with something else than "true" or "false"
+    InvalidListen(Span, String),
+    // children next to a `slot="builder"`, which builds all of them
+    ChildrenBesideBuilder(Span, String),
+    // a `<Self>` anywhere but as the only root of a widget's body
+    MisplacedSelf(Span),
+    // a `<Self>` with a style, attributes, arguments, events or content
+    SelfWithOptions(Span),
+    // the parser gave up on this part of the tree
+    ErrorNode(Span),
+}
+
+impl EmitError {
+    pub fn to_diagnostic(&self, file: &str) -> Diagnostic {
+        let (span, message) = match self {
+            EmitError::EmptyProgram(span) => (*span, String::from("nothing to build")),
+            EmitError::MultipleRoots(span, count) => (
+                *span,
+                format!("expected a single root widget, found {}", count),
+            ),
+            EmitError::UnknownStyle(span, key) => (*span, format!("unknown style `{}`", key)),
+            EmitError::InvalidStyleValue(span, key, value) => (
+                *span,
+                format!("invalid value `{}` for style `{}`", value, key),
+            ),
+            EmitError::DuplicateStyle(span, key, first) => {
+                return Diagnostic::error(file, *span, &format!("style `{}` is given twice", key))
+                    .with_label(*first, "first given here")
+            }
+            EmitError::UnknownEvent(span, widget, event) => {
+                (*span, format!("`{}` has no `@{}` event", widget, event))
+            }
+            EmitError::DuplicateSlot(span, widget, slot) => (
+                *span,
+                format!("slot `{}` of `{}` is filled twice", slot, widget),
+            ),
+            EmitError::UnboundIdentifier(span, name) => {
+                (*span, format!("cannot find `{}` in this scope", name))
+            }
+            EmitError::DuplicateArgument(span, widget, name) => (
+                *span,
+                format!("`{}` is given the argument `{}` twice", widget, name),
+            ),
+            EmitError::UnknownArgument(span, widget, name) => {
+                (*span, format!("`{}` has no argument `{}`", widget, name))
+            }
+            EmitError::MissingArgument(span, widget, name) => (
+                *span,
+                format!("`{}` is missing the required argument `{}`", widget, name),
+            ),
+            EmitError::UnexpectedContent(span, widget) => {
+                (*span, format!("`{}` takes no content", widget))
+            }
+            EmitError::ChildCount(span, widget, arity, found, slots) => {
+                let message = format!("`{}` takes {}, found {}", widget, arity.describe(), found);
+                let diagnostic = Diagnostic::error(file, *span, &message);
+                guard_clause!(slots.is_empty(), diagnostic);
+                return diagnostic.with_note(&format!(
+                    "other widgets go in its slots, with `slot=\"{}\"`",
+                    slots.join("\"` or `slot=\"")
+                ));
+            }
+            EmitError::EmptyBlock(span) => (*span, String::from("nothing to show under this tag")),
+            EmitError::BranchChildCount(span) => {
+                return Diagnostic::error(
+                    file,
+                    *span,
+                    "each branch must hold exactly one widget where a single child goes",
+                )
+                .with_note("wrap the widgets of a branch in a `<Column>` or `<Row>`")
+            }
+            EmitError::SlotInBlock(span, tag) => (
+                *span,
+                format!("widgets inside `<{}>` cannot fill a slot", tag),
+            ),
+            EmitError::SingleChildLoop(span) => (
+                *span,
+                String::from("`<for>` can only build the children of a widget taking several"),
+            ),
+            EmitError::KeyedLoopChildren(span) => (
+                *span,
+                String::from("`key:` needs the loop to build a single widget for each item"),
+            ),
+            EmitError::AssignToNonState(span, name) => {
+                let message = match name {
+                    Some(name) => format!("cannot assign to `{}`, it is not a `state`", name),
+                    None => String::from("cannot assign to this expression"),
+                };
+                return Diagnostic::error(file, *span, &message).with_note(
+                    "handlers can only assign to what the widget declares with `state name: Type = value`",
+                );
+            }
+            EmitError::DuplicateState(span, name) => (
+                *span,
+                format!("`{}` is declared twice in this widget", name),
+            ),
+            EmitError::InvalidListen(span, value) => (
+                *span,
+                format!(
+                    "`{}` takes \"true\" or \"false\", found \"{}\"",
+                    LISTEN_ATTRIBUTE, value
+                ),
+            ),
+            EmitError::ChildrenBesideBuilder(span, widget) => {
+                return Diagnostic::error(
+                    file,
+                    *span,
+                    &format!("`{}` builds its children with `slot=\"builder\"`", widget),
+                )
+                .with_note(
+                    "the builder's child is built once for each index, give it all the items",
+                )
+            }
+            EmitError::MisplacedSelf(span) => (
+                *span,
+                format!(
+                    "`<{}>` can only be the root of a widget's body",
+                    SELF_WIDGET
+                ),
+            ),
+            EmitError::SelfWithOptions(span) => {
+                return Diagnostic::error(
+                    file,
+                    *span,
+                    &format!("`<{}>` only holds the widget's children", SELF_WIDGET),
+                )
+                .with_note("give the style, arguments and events to a widget under it")
+            }
+            EmitError::ErrorNode(span) => (
+                *span,
+                String::from("cannot generate code for a part that failed to parse"),
+            ),
+        };
+
+        Diagnostic::error(file, span, &message)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct EmitOptions {
+    pub events: EventMap,
+    pub catalog: Catalog,
+    pub palette: Palette,
+    // the project's own listenable classes, next to Flutter's
+    pub listenables: Vec<String>,
+}
+
+pub fn emit_program(items: &[Item], options: &EmitOptions) -> Result<String, EmitError> {
+    let mut imports = Vec::new();
+    let mut decls = Vec::new();
+    let mut widgets = Vec::new();
+    let mut trailing = String::new();
+    // a Dart import may bring any top-level name, which only Dart can check
+    let imports_dart = items
+        .iter()
+        .any(|item| matches!(item, Item::Import(import) if !is_markup(&import.path)));
+
+    for item in items {
+        match item {
+            Item::Import(import) => imports.push(emit_import(import)),
+            Item::WidgetDecl(decl) => decls.push(emit_widget_decl(decl, options, imports_dart)?),
+            Item::Error(error) => return Err(EmitError::ErrorNode(error.span)),
+            Item::Widget(widget) => widgets.push((
+                widget.span,
+                emit_widget(widget, options, &Scope::unchecked())?
+                    .with_comments(dart_comments(&widget.comments)),
+            )),
+            Item::Comments(comments) => {
+                trailing = dart_comments(&comments.comments)
+                    .iter()
+                    .map(|comment| format!("{}\n", comment))
+                    .collect()
+            }
+        }
+    }
+
+    if !decls.is_empty() {
+        imports.insert(0, format!("import '{}';\n", FLUTTER_IMPORT));
+    }
+
+    let mut sections = Vec::new();
+    if !imports.is_empty() {
+        sections.push(imports.concat());
+    }
+    sections.append(&mut decls);
+    // a file of nothing but comments compiles to them alone
+    if !widgets.is_empty() || sections.is_empty() && trailing.is_empty() {
+        sections.push(emit_build(&widgets, Span::default(), 0)?);
+    }
+    if !trailing.is_empty() {
+        sections.push(trailing);
+    }
+
+    Ok(sections.join("\n"))
+}
+
+// Markup files are compiled next to themselves, so `import "x.flutter"` becomes `import 'x.dart'`.
+pub fn emit_import(import: &Import) -> String {
+    let path = match is_markup(&import.path) {
+        true => Path::new(&import.path)
+            .with_extension("dart")
+            .to_string_lossy()
+            .into_owned(),
+        false => import.path.clone(),
+    };
+
+    let comments: String = dart_comments(&import.comments)
+        .iter()
+        .map(|comment| format!("{}\n", comment))
+        .collect();
+    format!("{}import '{}';\n", comments, path)
+}
+
+fn is_markup(path: &str) -> bool {
+    Path::new(path).extension().and_then(|ext| ext.to_str()) == Some(MARKUP_EXTENSION)
+}
+
+pub fn emit_widget_decl(
+    decl: &WidgetDecl,
+    options: &EmitOptions,
+    imports_dart: bool,
+) -> Result<String, EmitError> {
+    let body = match decl.body.as_slice() {
+        [Node::Widget(root)] if root.name == SELF_WIDGET => {
+            let options_given = !root.style.is_empty()
+                || !root.attributes.is_empty()
+                || !root.props.is_empty()
+                || !root.events.is_empty()
+                || root.content.is_some();
+            guard_clause!(options_given, Err(EmitError::SelfWithOptions(root.span)));
+            &root.children
+        }
+        _ => &decl.body,
+    };
+    let params = decl.params.iter().map(|param| param.name.as_str());
+    let states = decl.states.iter().map(|state| state.name.as_str());
+    let scope = match decl.states.is_empty() {
+        true => Scope::new(params),
+        false => Scope::stateful(params, states),
+    };
+    let scope = match imports_dart {
+        true => scope.unchecked_names(),
+        false => scope,
+    };
+    let scope = scope.listening(
+        decl.params
+            .iter()
+            .filter(|param| is_listenable(&param.ty, &options.listenables))
+            .map(|param| param.name.as_str()),
+    );
+    // a conditional root has no widget above it to rebuild
+    let reads = block_reads(body, &scope);
+    let inner = unlistened(&scope, &reads);
+    let roots = body
+        .iter()
+        .map(|node| {
+            let root = single_child(node.span(), emit_node(node, options, &inner)?, false)?;
+            Ok((node.span(), listen(root, &reads, &scope)?))
+        })
+        .collect::<Result<Vec<(Span, DartExpr)>, EmitError>>()?;
+
+    let mut constructor_params = vec![String::from("super.key")];
+    let mut fields = String::new();
+    for param in &decl.params {
+        constructor_params.push(format!("required this.{}", param.name));
+        fields += &format!("  final {} {};\n", param.ty, param.name);
+    }
+
+    let mut out: String = dart_comments(&decl.comments)
+        .iter()
+        .map(|comment| format!("{}\n", comment))
+        .collect();
+    let base = match decl.states.is_empty() {
+        true => "StatelessWidget",
+        false => "StatefulWidget",
+    };
+    out += &format!("class {} extends {} {{\n", decl.name, base);
+    out += &format!(
+        "  const {}({{{}}});\n\n",
+        decl.name,
+        constructor_params.join(", ")
+    );
+    if !fields.is_empty() {
+        out += &(fields + "\n");
+    }
+    if decl.states.is_empty() {
+        out += &emit_build(&roots, decl.span, 1)?;
+        out += "}\n";
+        return Ok(out);
+    }
+
+    let state_class = format!("_{}State", decl.name);
+    out += "  @override\n";
+    out += &format!(
+        "  State<{}> createState() => {}();\n}}\n\n",
+        decl.name, state_class
+    );
+    out += &format!("class {} extends State<{}> {{\n", state_class, decl.name);
+    out += &emit_states(decl, &scope)?;
+    out += &emit_build(&roots, decl.span, 1)?;
+    out += "}\n";
+
+    Ok(out)
+}
+
+// The fields of the `State`, `late` when their initial value reads the widget or
+// another state, which a plain field initializer cannot.
+fn emit_states(decl: &WidgetDecl, scope: &Scope) -> Result<String, EmitError> {
+    let mut out = String::new();
+    for (index, state) in decl.states.iter().enumerate() {
+        let taken = decl
+            .params
+            .iter()
+            .map(|param| param.name.as_str())
+            .chain(decl.states[..index].iter().map(|state| state.name.as_str()));
+        for name in taken {
+            guard_clause!(
+                name == state.name,
+                Err(EmitError::DuplicateState(state.span, state.name.clone()))
+            );
+        }
+
+        let reads_members = state.init.identifiers().into_iter().any(|name| {
+            decl.params.iter().any(|param| param.name == name)
+                || decl.states.iter().any(|state| state.name == name)
+        });
+        let late = if reads_members { "late " } else { "" };
+
+        for comment in dart_comments(&state.comments) {
+            out += &format!("  {}\n", comment);
+        }
+        out += &format!(
+            "  {}{} {} = {};\n",
+            late,
+            state.ty,
+            state.name,
+            emit_expr(&state.init, scope)?
+        );
+    }
+
+    Ok(out + "\n")
+}
+
+pub fn emit_node(node: &Node, options: &EmitOptions, scope: &Scope) -> Result<DartExpr, EmitError> {
+    match node {
+        Node::Widget(widget) => {
+            Ok(emit_widget(widget, options, scope)?.with_comments(dart_comments(&widget.comments)))
+        }
+        Node::Text(text) => {
+            let call = DartExpr::call(TEXT_WIDGET)
+                .with_positional(DartExpr::raw(&emit_expr(&text.content, scope)?));
+            Ok(listen(call, &expr_reads(&text.content, scope), scope)?
+                .with_comments(dart_comments(&text.comments)))
+        }
+        Node::If(node) => {
+            Ok(emit_if(node, options, scope)?.with_comments(dart_comments(&node.comments)))
+        }
+        Node::For(node) => {
+            Ok(emit_for(node, options, scope)?.with_comments(dart_comments(&node.comments)))
+        }
+        Node::Error(error) => Err(EmitError::ErrorNode(error.span)),
+    }
+}
+
+// Collection-if as it goes in `children: [...]`, `single_child` turns it into a
+// ternary where one widget goes.
+fn emit_if(node: &IfNode, options: &EmitOptions, scope: &Scope) -> Result<DartExpr, EmitError> {
+    let mut branches = Vec::new();
+    for branch in &node.branches {
+        let condition = match &branch.condition {
+            Some(condition) => Some(emit_expr(condition, scope)?),
+            None => None,
+        };
+
+        let mut elements = emit_block("if", branch.span, &branch.children, options, scope)?;
+        // comments before `<else>` go before the first widget of its branch
+        elements[0] = elements[0]
+            .clone()
+            .with_comments(dart_comments(&branch.comments));
+
+        branches.push((condition, elements));
+    }
+
+    Ok(DartExpr::If { branches })
+}
+
+// Collection-for as it goes in `children: [...]`, `with_children` turns it into a
+// builder for lazy lists.
+fn emit_for(node: &ForNode, options: &EmitOptions, scope: &Scope) -> Result<DartExpr, EmitError> {
+    let iterable = emit_expr(&node.iterable, scope)?;
+    let iterable = match node.iterable.kind {
+        ExprKind::Identifier(_)
+        | ExprKind::Member(..)
+        | ExprKind::Index(..)
+        | ExprKind::Call(..)
+        | ExprKind::Paren(_) => iterable,
+        // `.indexed` and `.length` would bind to the last operand
+        _ => format!("({})", iterable),
+    };
+    let scope = scope.with(
+        [node.item.as_str()]
+            .into_iter()
+            .chain(node.index.as_deref()),
+    );
+
+    let mut elements = emit_block("for", node.span, &node.children, options, &scope)?;
+    if let Some(key) = &node.key {
+        let widget = match node.children.as_slice() {
+            [Node::Widget(widget)] => widget.name.as_str(),
+            [Node::Text(_)] => TEXT_WIDGET,
+            _ => return Err(EmitError::KeyedLoopChildren(key.span)),
+        };
+        let key_expr = DartExpr::raw(&format!("ValueKey({})", emit_expr(key, &scope)?));
+        let keyed = with_key(elements.remove(0), key_expr).ok_or_else(|| {
+            EmitError::DuplicateArgument(key.span, widget.to_string(), KEY_ARGUMENT.to_string())
+        })?;
+        elements = vec![keyed];
+    }
+
+    Ok(DartExpr::For {
+        item: node.item.clone(),
+        index: node.index.clone(),
+        iterable,
+        elements,
+    })
+}
+
+// the children of `<if>` and `<for>`, which are never slotted
+fn emit_block(
+    tag: &str,
+    span: Span,
+    children: &[Node],
+    options: &EmitOptions,
+    scope: &Scope,
+) -> Result<Vec<DartExpr>, EmitError> {
+    guard_clause!(children.is_empty(), Err(EmitError::EmptyBlock(span)));
+
+    let mut elements = Vec::new();
+    for child in children {
+        guard_clause!(
+            slot_of(child).is_some(),
+            Err(EmitError::SlotInBlock(child.span(), tag.to_string()))
+        );
+        elements.push(emit_node(child, options, scope)?);
+    }
+    Ok(elements)
+}
+
+// `None` when the widget already has a key
+fn with_key(element: DartExpr, key: DartExpr) -> Option<DartExpr> {
+    match element {
+        DartExpr::Commented { comments, expr } => {
+            Some(with_key(*expr, key)?.with_comments(comments))
+        }
+        _ if element.has_named(KEY_ARGUMENT) => None,
+        _ => Some(element.with_named(KEY_ARGUMENT, key)),
+    }
+}
+
+// A conditional where a single widget goes becomes a ternary. Without `<else>` it
+// shows nothing: `null` where the argument may be left out, an empty box otherwise.
+fn single_child(span: Span, expr: DartExpr, nullable: bool) -> Result<DartExpr, EmitError> {
+    guard_clause!(
+        matches!(expr.split_comments("").1, DartExpr::For { .. }),
+        Err(EmitError::SingleChildLoop(span))
+    );
+    let otherwise = DartExpr::raw(if nullable { "null" } else { EMPTY_WIDGET });
+    expr.into_ternary(otherwise)
+        .ok_or(EmitError::BranchChildCount(span))
+}
+
+pub fn emit_widget(
+    widget: &Widget,
+    options: &EmitOptions,
+    scope: &Scope,
+) -> Result<DartExpr, EmitError> {
+    guard_clause!(
+        widget.name == SELF_WIDGET,
+        Err(EmitError::MisplacedSelf(widget.span))
+    );
+    let spec = options.catalog.get(&widget.name);
+    let reads = widget_reads(widget, &options.catalog, scope)?;
+    let outer = scope;
+    let scope = &match listens(widget)? {
+        true => unlistened(outer, &reads),
+        false => scope.listening([]),
+    };
+
+    let mut slots: Vec<(&str, Span, DartExpr)> = Vec::new();
+    let mut children = Vec::new();
+    let mut item_builder = None;
+    for child in &widget.children {
+        if let Some((lazy, builder)) = item_builder_of(child, spec) {
+            guard_clause!(
+                item_builder.is_some(),
+                Err(EmitError::DuplicateSlot(
+                    child.span(),
+                    widget.name.clone(),
+                    BUILDER_SLOT.to_string()
+                ))
+            );
+            item_builder = Some((lazy, emit_item_builder(builder, options, scope)?));
+            continue;
+        }
+        let expr = emit_node(child, options, scope)?;
+
+        match slot_of(child) {
+            Some(slot) if slots.iter().any(|(name, ..)| *name == slot) => {
+                return Err(EmitError::DuplicateSlot(
+                    child.span(),
+                    widget.name.clone(),
+                    slot.to_string(),
+                ))
+            }
+            Some(slot) => slots.push((slot, child.span(), expr)),
+            None => children.push((child.span(), expr)),
+        }
+    }
+
+    let mut call = DartExpr::call(&widget.name);
+
+    if let Some(content) = &widget.content {
+        guard_clause!(
+            spec.is_some_and(|spec| spec.positional.is_empty()),
+            Err(EmitError::UnexpectedContent(
+                content.span,
+                widget.name.clone()
+            ))
+        );
+        call = call.with_positional(DartExpr::raw(&emit_expr(content, scope)?));
+    }
+
+    for attribute in &widget.attributes {
+        if attribute.name == SLOT_ATTRIBUTE || attribute.name == LISTEN_ATTRIBUTE {
+            continue;
+        }
+        let value = DartExpr::raw(&dart_string(&attribute.value));
+        call = with_argument(call, widget, spec, attribute.span, &attribute.name, value)?;
+    }
+
+    for prop in &widget.props {
+        let value = DartExpr::raw(&emit_expr(&prop.value, scope)?);
+        call = with_argument(call, widget, spec, prop.span, &prop.name, value)?;
+    }
+
+    for event in &widget.events {
+        let param = options
+            .events
+            .resolve(&widget.name, &event.name)
+            .ok_or_else(|| {
+                EmitError::UnknownEvent(event.span, widget.name.clone(), event.name.clone())
+            })?;
+        let handler = DartExpr::raw(&emit_handler(&event.handler, spec, param, scope)?);
+        call = with_argument(call, widget, spec, event.span, param, handler)?;
+    }
+
+    for (slot, span, expr) in slots {
+        call = with_argument(call, widget, spec, span, slot, expr)?;
+    }
+
+    if let (Some(slot), [_]) = (
+        spec.and_then(|spec| spec.default_slot.as_deref()),
+        &*children,
+    ) {
+        let (span, child) = children.remove(0);
+        let nullable = spec
+            .and_then(|spec| spec.named(slot))
+            .is_some_and(|param| param.ty.ends_with('?'));
+        let child = single_child(span, child, nullable)?;
+        call = with_argument(call, widget, spec, span, slot, child)?;
+    }
+
+    let call = match (spec, item_builder) {
+        (_, Some((lazy, (count, closure)))) => {
+            if let Some((span, _)) = children.first() {
+                return Err(EmitError::ChildrenBesideBuilder(*span, widget.name.clone()));
+            }
+            call.with_callee(lazy)
+                .with_named(ITEM_COUNT_ARGUMENT, count)
+                .with_named(ITEM_BUILDER_ARGUMENT, closure)
+        }
+        (Some(spec), None) => with_children(call, widget, spec, children)?,
+        (None, None) => match children.len() {
+            0 => call,
+            1 => {
+                let (span, child) = children.remove(0);
+                call.with_named("child", single_child(span, child, false)?)
+            }
+            _ => call.with_named("children", unspanned(children)),
+        },
+    };
+
+    listen(
+        apply_style(call, &widget.style, &options.palette)?,
+        &reads,
+        outer,
+    )
+}
+
+/// The `<Builder slot="builder">` among the children of a widget with a lazy
+/// constructor, with that constructor.
+pub(super) fn item_builder_of<'a>(
+    node: &'a Node,
+    spec: Option<&'a WidgetSpec>,
+) -> Option<(&'a str, &'a Widget)> {
+    let lazy = spec?.builder.as_deref()?;
+    match node {
+        Node::Widget(widget)
+            if widget.name == BUILDER_WIDGET && slot_of(node) == Some(BUILDER_SLOT) =>
+        {
+            Some((lazy, widget))
+        }
+        _ => None,
+    }
+}
+
+// `itemCount: n` and `itemBuilder: (context, index) => ...` from the builder's
+// `itemCount:` and only child, which sees the index as `index`
+fn emit_item_builder(
+    builder: &Widget,
+    options: &EmitOptions,
+    scope: &Scope,
+) -> Result<(DartExpr, DartExpr), EmitError> {
+    let unknown = |span: Span, name: &str| {
+        EmitError::UnknownArgument(span, builder.name.clone(), name.to_string())
+    };
+    let mut arguments = builder
+        .attributes
+        .iter()
+        .filter(|attribute| attribute.name != SLOT_ATTRIBUTE)
+        .map(|attribute| (attribute.span, attribute.name.as_str()))
+        .chain(
+            builder
+                .events
+                .iter()
+                .map(|event| (event.span, event.name.as_str())),
+        );
+    if let Some((span, name)) = arguments.next() {
+        return Err(unknown(span, name));
+    }
+    if let Some(content) = &builder.content {
+        return Err(EmitError::UnexpectedContent(
+            content.span,
+            builder.name.clone(),
+        ));
+    }
+
+    let mut count = None;
+    for prop in &builder.props {
+        guard_clause!(
+            prop.name != ITEM_COUNT_ARGUMENT,
+            Err(unknown(prop.span, &prop.name))
+        );
+        guard_clause!(
+            count.is_some(),
+            Err(EmitError::DuplicateArgument(
+                prop.span,
+                builder.name.clone(),
+                prop.name.clone()
+            ))
+        );
+        count = Some(DartExpr::raw(&emit_expr(&prop.value, scope)?));
+    }
+    let count = count.ok_or_else(|| {
+        EmitError::MissingArgument(
+            builder.span,
+            builder.name.clone(),
+            ITEM_COUNT_ARGUMENT.to_string(),
+        )
+    })?;
+
+    let item = match builder.children.as_slice() {
+        [child] => {
+            let expr = emit_node(child, options, &scope.with([INDEX_PARAMETER]))?;
+            single_child(child.span(), expr, false)?
+        }
+        children => {
+            return Err(EmitError::ChildCount(
+                builder.span,
+                builder.name.clone(),
+                ChildArity::One,
+                children.len(),
+                Vec::new(),
+            ))
+        }
+    };
+    let closure = DartExpr::Closure {
+        params: vec![String::from("context"), String::from(INDEX_PARAMETER)],
+        statements: Vec::new(),
+        result: Box::new(apply_style(item, &builder.style, &options.palette)?),
+    };
+
+    Ok((count, closure))
+}
+
+// the scope under a `ListenableBuilder` for `reads`, which need no other
+fn unlistened(scope: &Scope, reads: &[String]) -> Scope {
+    let left: Vec<&str> = scope
+        .listenables()
+        .filter(|name| !reads.iter().any(|read| read == name))
+        .collect();
+    scope.listening(left)
+}
+
+// A tear-off, `controller.add`, is the callback itself. Anything else runs when
+// the callback is called: `go(1)` becomes `() => go(1)`. An assignment only type
+// checks inside a `State`, where it's wrapped in `setState` so that the widget
+// rebuilds. Callbacks given a value, like `onChanged`, pass it on as `value`.
+fn emit_handler(
+    handler: &Expr,
+    spec: Option<&WidgetSpec>,
+    param: &str,
+    scope: &Scope,
+) -> Result<String, EmitError> {
+    guard_clause!(
+        matches!(handler.kind, ExprKind::Identifier(_) | ExprKind::Member(..)),
+        emit_expr(handler, scope)
+    );
+
+    let takes_value = spec
+        .and_then(|spec| spec.named(param))
+        .is_some_and(|param| param.ty.starts_with("ValueChanged"));
+    let (params, scope) = match takes_value {
+        true => (VALUE_PARAMETER, scope.with([VALUE_PARAMETER])),
+        false => ("", scope.clone()),
+    };
+
+    let body = emit_expr(handler, &scope)?;
+    match handler.kind {
+        ExprKind::Assign(..) => Ok(format!("({}) => setState(() => {})", params, body)),
+        _ => Ok(format!("({}) => {}", params, body)),
+    }
+}
+
+// a named argument may come from an attribute, a prop, an event or a slot, but only
+// once, and only if the catalog knows it
+fn with_argument(
+    call: DartExpr,
+    widget: &Widget,
+    spec: Option<&WidgetSpec>,
+    span: Span,
+    name: &str,
+    value: DartExpr,
+) -> Result<DartExpr, EmitError> {
+    guard_clause!(
+        call.has_named(name),
+        Err(EmitError::DuplicateArgument(
+            span,
+            widget.name.clone(),
+            name.to_string()
+        ))
+    );
+    guard_clause!(
+        spec.is_some_and(|spec| name != KEY_ARGUMENT && spec.named(name).is_none()),
+        Err(EmitError::UnknownArgument(
+            span,
+            widget.name.clone(),
+            name.to_string()
+        ))
+    );
+
+    Ok(call.with_named(name, value))
+}
+
+// Passes the children the way the catalog says the widget takes them, then checks
+// that nothing it requires is missing.
+fn with_children(
+    call: DartExpr,
+    widget: &Widget,
+    spec: &WidgetSpec,
+    mut children: Vec<(Span, DartExpr)>,
+) -> Result<DartExpr, EmitError> {
+    let most = match spec.children {
+        ChildArity::None => 0,
+        ChildArity::Optional | ChildArity::One => 1,
+        ChildArity::Many => usize::MAX,
+    };
+    if let Some((span, _)) = children.get(most) {
+        let slots = spec.slots.iter().map(|slot| slot.name.clone()).collect();
+        return Err(EmitError::ChildCount(
+            *span,
+            widget.name.clone(),
+            spec.children,
+            children.len(),
+            slots,
+        ));
+    }
+
+    let call = match spec.children {
+        ChildArity::Many => match (&spec.builder, children.len()) {
+            (Some(builder), 1) => with_builder(call, builder, children.remove(0))?,
+            _ => call.with_named("children", unspanned(children)),
+        },
+        _ if children.is_empty() => call,
+        _ => {
+            let (span, child) = children.remove(0);
+            let nullable = spec.children == ChildArity::Optional;
+            call.with_named("child", single_child(span, child, nullable)?)
+        }
+    };
+
+    let missing_child = spec.children == ChildArity::One && !call.has_named("child");
+    let missing = spec
+        .positional
+        .iter()
+        .filter(|param| param.required && widget.content.is_none())
+        .chain(
+            spec.params
+                .iter()
+                .chain(&spec.slots)
+                .filter(|param| param.required && !call.has_named(&param.name)),
+        )
+        .map(|param| param.name.as_str())
+        .chain(missing_child.then_some("child"))
+        .next();
+    match missing {
+        Some(name) => Err(EmitError::MissingArgument(
+            widget.span,
+            widget.name.clone(),
+            name.to_string(),
+        )),
+        None => Ok(call),
+    }
+}
+
+// `ListView.builder(itemCount: ..., itemBuilder: ...)` when the only child is a
+// `<for>` building one widget per item, so that only the items on screen are built.
+// The loop must go over a list by name, which the builder indexes.
+fn with_builder(
+    call: DartExpr,
+    builder: &str,
+    (span, expr): (Span, DartExpr),
+) -> Result<DartExpr, EmitError> {
+    let (comments, expr) = match expr {
+        DartExpr::Commented { comments, expr } => (comments, *expr),
+        expr => (Vec::new(), expr),
+    };
+
+    match expr {
+        DartExpr::For {
+            item,
+            index,
+            iterable,
+            mut elements,
+        } if elements.len() == 1 && is_path(&iterable) => {
+            let index =
+                index.unwrap_or_else(|| String::from(if item == "index" { "i" } else { "index" }));
+            let closure = DartExpr::Closure {
+                statements: vec![format!("final {} = {}[{}];", item, iterable, index)],
+                params: vec![String::from("context"), index],
+                result: Box::new(single_child(span, elements.remove(0), false)?),
+            };
+
+            Ok(call
+                .with_callee(builder)
+                .with_named("itemCount", DartExpr::raw(&format!("{}.length", iterable)))
+                .with_named("itemBuilder", closure.with_comments(comments)))
+        }
+        expr => Ok(call.with_named(
+            "children",
+            DartExpr::List(vec![expr.with_comments(comments)]),
+        )),
+    }
+}
+
+// `items` or `controller.items`, rather than a call or an operation that would
+// run again for each index and may not give something indexable
+fn is_path(iterable: &str) -> bool {
+    iterable.split('.').all(|name| {
+        name.starts_with(|ch: char| ch.is_alphabetic() || ch == '_')
+            && name.chars().all(|ch| ch.is_alphanumeric() || ch == '_')
+    })
+}
+
+fn unspanned(children: Vec<(Span, DartExpr)>) -> DartExpr {
+    DartExpr::List(children.into_iter().map(|(_, expr)| expr).collect())
+}
+
+pub(super) fn slot_of(node: &Node) -> Option<&str> {
+    let widget = match node {
+        Node::Widget(widget) => widget,
+        Node::Text(_) | Node::If(_) | Node::For(_) | Node::Error(_) => return None,
+    };
+
+    widget
+        .attributes
+        .iter()
+        .find(|attribute| attribute.name == SLOT_ATTRIBUTE)
+        .map(|attribute| attribute.value.as_str())
+}
+
+// `// x` and `/* x */` stay as they are, markup comments and comments spanning several
+// lines become one `//` line per line, since they are re-indented with the Dart code.
+fn dart_comments(comments: &[Comment]) -> Vec<String> {
+    let mut lines = Vec::new();
+    for comment in comments {
+        match comment.kind {
+            CommentKind::Line => lines.push(format!("//{}", comment.text)),
+            CommentKind::Block if !comment.text.contains('\n') => {
+                lines.push(format!("/*{}*/", comment.text))
+            }
+            CommentKind::Block | CommentKind::Markup => lines.extend(
+                comment
+                    .text
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty())
+                    .map(|line| format!("// {}", line)),
+            ),
+        }
+    }
+    lines
+}
+
+// `build()` returning the only root, `span` is where the roots should be if there are none
+pub fn emit_build(
+    roots: &[(Span, DartExpr)],
+    span: Span,
+    depth: usize,
+) -> Result<String, EmitError> {
+    let root = match roots {
+        [] => return Err(EmitError::EmptyProgram(span)),
+        [(_, root)] => root,
+        [_, (second, _), ..] => {
+            let (last, _) = roots[roots.len() - 1];
+            return Err(EmitError::MultipleRoots(second.to(last), roots.len()));
+        }
+    };
+
+    let pad = "  ".repeat(depth);
+    let (comments, root) = root.split_comments(&format!("{pad}  "));
+    Ok(format!(
+        "{pad}@override\n{pad}Widget build(BuildContext context) {{\n{comments}{pad}  return {};\n{pad}}}\n",
+        root.render(depth + 1)
+    ))
+}
+
+golden_test!(emit_button_with_several_children, "button_children");
+golden_test!(emit_deeply_nested_single_children, "nested_container");
+golden_test!(emit_counter_page_layout, "counter_layout");
+golden_test!(emit_widget_declaration, "widget_decl");
+golden_test!(emit_imports_before_declarations, "imports");
+golden_test!(emit_style_blocks_as_wrappers, "style");
+golden_test!(emit_event_bindings_as_callbacks, "events");
+golden_test!(emit_slotted_children_as_named_arguments, "slots");
+golden_test!(emit_text_content_as_positional_argument, "content");
+golden_test!(emit_string_interpolation, "interpolation");
+golden_test!(emit_expressions, "expressions");
+golden_test!(emit_props_as_named_arguments, "props");
+golden_test!(emit_builder_slot_as_a_lazy_constructor, "builder");
+golden_test!(emit_states_as_a_stateful_widget, "state");
+golden_test!(emit_loops_as_collection_for_and_builders, "loops");
+golden_test!(emit_listenable_reads_in_listenable_builders, "listenable");
+golden_test!(emit_comments_as_dart_comments, "comments");
+golden_test!(
+    emit_conditionals_as_collection_if_and_ternaries,
+    "conditionals"
+);
+
+#[cfg(test)]
+fn emit_source(src: &str) -> Result<String, EmitError> {
+    let tokens = crate::lexer::lexer::lex(src).unwrap();
+    let ast = crate::parser::parser::parse_program(&mut std::collections::VecDeque::from(tokens));
+    emit_program(&ast.into_result().unwrap(), &EmitOptions::default())
+}
+
+#[test]
+fn two_children_in_the_same_slot_is_an_error() {
+    let got = emit_source("<Scaffold>\n  <AppBar slot=\"appBar\">\n  <Text slot=\"appBar\"> \"x\"");
+
+    assert!(
+        matches!(got, Err(EmitError::DuplicateSlot(..))),
+        "{:?} should be a duplicate slot",
+        got
+    );
+}
+
+#[test]
+fn unbound_identifier_in_declaration_is_an_error() {
+    let got = emit_source("widget Counter(controller: Controller)\n  <Text> \"${count}\"");
+
+    assert!(got.is_err(), "{:?} should be an error", got);
+}
+
+#[test]
+fn declarations_see_the_build_context() {
+    let got = emit_source(
+        "widget Title(name: String)\n  <Text style:Theme.of(context).textTheme.titleLarge> \"${name}\"",
+    )
+    .unwrap();
+
+    assert!(
+        got.contains("Text(\"$name\", style: Theme.of(context).textTheme.titleLarge)"),
+        "{}",
+        got
+    );
+}
+
+#[test]
+fn same_argument_from_a_prop_and_a_slot_is_an_error() {
+    let got = emit_source("<Scaffold body:controller.body>\n  <Column slot=\"body\">");
+
+    assert!(got.is_err(), "{:?} should be an error", got);
+}
+
+#[test]
+fn argument_missing_from_the_catalog_is_an_error() {
+    let got = emit_source("<Icon @tap:controller.add> Icons.add");
+
+    assert!(
+        matches!(&got, Err(EmitError::UnknownArgument(_, widget, name)) if widget == "Icon" && name == "onTap"),
+        "{:?} should be an unknown argument",
+        got
+    );
+}
+
+#[test]
+fn required_argument_left_out_is_an_error() {
+    let got = emit_source("<Column>\n  <Padding>\n    <Text> \"x\"");
+
+    assert!(
+        matches!(&got, Err(EmitError::MissingArgument(span, _, name)) if name == "padding" && span.start == 11),
+        "{:?} should be a missing argument",
+        got
+    );
+    assert!(emit_source("<Text>").is_err());
+    assert!(emit_source("<Expanded>").is_err());
+}
+
+#[test]
+fn wrong_child_count_is_an_error() {
+    let got = emit_source("<Container>\n  <Text> \"a\"\n  <Text> \"b\"");
+
+    assert!(
+        matches!(&got, Err(EmitError::ChildCount(span, _, ChildArity::Optional, 2, _)) if span.start == 27),
+        "{:?} should be a child count error",
+        got
+    );
+    let source = "<Scaffold>\n  <Text> \"a\"\n  <Text> \"b\"";
+    let rendered = emit_source(source)
+        .unwrap_err()
+        .to_diagnostic("app.flutter")
+        .render(source);
+    assert!(
+        rendered.contains("`Scaffold` takes no children, found 2"),
+        "{}",
+        rendered
+    );
+    assert!(
+        rendered.contains("slot=\"appBar\"` or `slot=\"body\""),
+        "{}",
+        rendered
+    );
+}
+
+#[test]
+fn a_lone_child_goes_to_the_default_slot() {
+    let got = emit_source("<AppBar>\n  <Text> \"Title\"").unwrap();
+    assert!(
+        got.contains("AppBar(\n    title: Text(\"Title\"),"),
+        "{}",
+        got
+    );
+
+    let got = emit_source("<AppBar>\n  <Text slot=\"title\"> \"a\"\n  <Text> \"b\"");
+    assert!(
+        matches!(&got, Err(EmitError::DuplicateArgument(_, _, name)) if name == "title"),
+        "{:?} should fill `title:` twice",
+        got
+    );
+}
+
+#[test]
+fn builder_slot_takes_the_whole_list() {
+    let got = emit_source(
+        "<ListView>\n  <Builder slot=\"builder\" itemCount:3>\n    <Text> \"a\"\n  <Text> \"b\"",
+    );
+    assert!(
+        matches!(&got, Err(EmitError::ChildrenBesideBuilder(span, _)) if span.start == 67),
+        "{:?} should be children beside the builder",
+        got
+    );
+
+    let got = emit_source("<ListView>\n  <Builder slot=\"builder\">\n    <Text> \"a\"");
+    assert!(
+        matches!(&got, Err(EmitError::MissingArgument(_, _, name)) if name == "itemCount"),
+        "{:?} should miss `itemCount`",
+        got
+    );
+}
+
+#[test]
+fn if_without_else_shows_nothing_in_a_single_child() {
+    let got = emit_source("<Container>\n  <if loading>\n    <Text> \"...\"").unwrap();
+    assert!(
+        got.contains("child: loading ? Text(\"...\") : null,"),
+        "{}",
+        got
+    );
+
+    let got = emit_source("<Expanded>\n  <if loading>\n    <Text> \"...\"").unwrap();
+    assert!(
+        got.contains("child: loading ? Text(\"...\") : const SizedBox.shrink(),"),
+        "{}",
+        got
+    );
+}
+
+#[test]
+fn branches_of_a_single_child_hold_one_widget() {
+    let got = emit_source("<Center>\n  <if a>\n    <Text> \"x\"\n    <Text> \"y\"");
+
+    assert!(
+        matches!(got, Err(EmitError::BranchChildCount(span)) if span.start == 11),
+        "{:?} should be a branch child count error",
+        got
+    );
+    assert!(matches!(
+        emit_source("<Column>\n  <if a>\n  <else>\n    <Divider>"),
+        Err(EmitError::EmptyBlock(_))
+    ));
+}
+
+#[test]
+fn loop_bindings_are_only_in_scope_inside_the_loop() {
+    let src = "widget Log(entries: List<String>)\n  <Column>\n    <for entry, index in entries>\n      <Text> \"${index}: ${entry}\"\n    <Text> entry";
+
+    assert!(
+        matches!(emit_source(src), Err(EmitError::UnboundIdentifier(span, name)) if name == "entry" && span.start == 124),
+        "only the last `entry` should be unbound"
+    );
+}
+
+#[test]
+fn dart_names_the_markup_cannot_see_are_not_checked() {
+    let got = emit_source("widget Filler()\n  <SizedBox width:double.infinity>");
+    assert!(got.is_ok(), "{:?}", got);
+
+    let got = emit_source(concat!(
+        "import \"format.dart\";\n",
+        "widget Count(n: int)\n",
+        "  <Text> formatCount(n)",
+    ));
+    assert!(got.is_ok(), "{:?}", got);
+    assert!(emit_source("widget Count(n: int)\n  <Text> formatCount(n)").is_err());
+}
+
+#[test]
+fn loop_needs_a_widget_taking_several_children() {
+    let got = emit_source("<Center>\n  <for item in items>\n    <Text> item");
+
+    assert!(
+        matches!(got, Err(EmitError::SingleChildLoop(span)) if span.start == 11),
+        "{:?} should be a single child loop error",
+        got
+    );
+}
+
+#[test]
+fn keyed_loop_builds_a_single_widget_per_item() {
+    let got =
+        emit_source("<Column>\n  <for item in items key:item.id>\n    <Text> item\n    <Divider>");
+    assert!(
+        matches!(got, Err(EmitError::KeyedLoopChildren(_))),
+        "{:?}",
+        got
+    );
+
+    let got = emit_source("<Column>\n  <for item in items key:item>\n    <Text key:k> item");
+    assert!(
+        matches!(got, Err(EmitError::DuplicateArgument(..))),
+        "{:?}",
+        got
+    );
+}
+
+#[test]
+fn handlers_other_than_tear_offs_run_when_called() {
+    let got = emit_source("<ElevatedButton @tap:go(1)>\n  <Text> \"Go\"").unwrap();
+    assert!(got.contains("onPressed: () => go(1),"), "{}", got);
+
+    let got = emit_source("<ElevatedButton @tap:controller.go>\n  <Text> \"Go\"").unwrap();
+    assert!(got.contains("onPressed: controller.go,"), "{}", got);
+}
+
+#[test]
+fn assigning_to_a_param_is_an_error() {
+    let got = emit_source(concat!(
+        "widget Counter(step: int)\n",
+        "  state count: int = 0\n",
+        "  <ElevatedButton @tap:step += 1>\n",
+        "    <Text> \"${count}\"",
+    ));
+
+    assert!(
+        matches!(&got, Err(EmitError::AssignToNonState(span, Some(name))) if name == "step" && span.start == 72),
+        "{:?} should be an assignment to a non-state",
+        got
+    );
+    assert!(emit_source("<InkWell @tap:count = 0>").is_err());
+}
+
+#[test]
+fn state_named_like_a_param_is_an_error() {
+    let got = emit_source("widget Counter(count: int)\n  state count: int = 0\n  <Text> \"x\"");
+
+    assert!(
+        matches!(&got, Err(EmitError::DuplicateState(_, name)) if name == "count"),
+        "{:?} should be a duplicate state",
+        got
+    );
+}
+
+#[test]
+fn loops_over_computed_lists_stay_collection_for() {
+    let got =
+        emit_source("<ListView>\n  <for name in names.where(isShort)>\n    <Text> name").unwrap();
+    assert!(
+        got.contains("for (final name in names.where(isShort)) Text(name),"),
+        "{}",
+        got
+    );
+
+    let got = emit_source("<ListView>\n  <for name in names>\n    <Text> name").unwrap();
+    assert!(got.contains("itemCount: names.length"), "{}", got);
+}
+
+#[test]
+fn root_count_errors_point_at_the_roots() {
+    let got = emit_source("<Center>\n<Spacer>\n<Divider>\n");
+    assert!(
+        matches!(got, Err(EmitError::MultipleRoots(span, 3)) if span == Span::new(9, 27)),
+        "{:?} should point at the roots after the first",
+        got
+    );
+
+    let got = emit_source("widget Empty()\n");
+    assert!(
+        matches!(got, Err(EmitError::EmptyProgram(span)) if span == Span::new(0, 14)),
+        "{:?} should point at the declaration",
+        got
+    );
+}
+
+#[test]
+fn comments_at_the_end_of_the_file_are_kept() {
+    let got = emit_source("<Center>\n// the end\n").unwrap();
+    assert!(got.ends_with("}\n\n// the end\n"), "{}", got);
+
+    assert_eq!(emit_source("// nothing yet\n").unwrap(), "// nothing yet\n");
+}
+
+#[test]
+fn self_only_holds_the_children_of_a_widget_body() {
+    let got = emit_source("widget Card()\n  <Self[p:4]>\n    <Text> \"x\"");
+    assert!(
+        matches!(got, Err(EmitError::SelfWithOptions(..))),
+        "{:?} should reject the style of <Self>",
+        got
+    );
+
+    for src in [
+        "<Self>\n  <Text> \"x\"",
+        "widget Card()\n  <Center>\n    <Self>\n      <Text> \"x\"",
+    ] {
+        let got = emit_source(src);
+        assert!(
+            matches!(got, Err(EmitError::MisplacedSelf(..))),
+            "{:?} should be a misplaced <Self>",
+            got
+        );
+    }
+}
+
+#[test]
+fn listen_takes_true_or_false() {
+    let got = emit_source(
+        "widget Counter(controller: CounterController)\n  <Text listen=\"never\"> controller.label",
+    );
+
+    assert!(
+        matches!(&got, Err(EmitError::InvalidListen(_, value)) if value == "never"),
+        "{:?} should be an invalid listen value",
+        got
+    );
+}
+
+#[test]
+fn loop_over_a_listenable_rebuilds_its_parent() {
+    let got = emit_source(concat!(
+        "widget History(controller: ValueNotifier<List<String>>)\n",
+        "  <Column>\n",
+        "    <Text> \"History\"\n",
+        "    <for entry in controller.value>\n",
+        "      <Text> \"${entry} of ${controller.value.length}\"",
+    ))
+    .unwrap();
+
+    assert_eq!(got.matches("ListenableBuilder(").count(), 1, "{}", got);
+    assert!(
+        got.contains("builder: (context, child) => Column("),
+        "{}",
+        got
+    );
+}
@@ -0,0 +1,74 @@
+use std::collections::HashMap;
+
+// Material buttons take `onPressed` where everything else takes `onTap`.
+const BUTTON_WIDGETS: [&str; 6] = [
+    "ElevatedButton",
+    "TextButton",
+    "OutlinedButton",
+    "FilledButton",
+    "IconButton",
+    "FloatingActionButton",
+];
+
+/// Maps short event names used in markup (`@tap:...`) to the Flutter constructor
+/// parameter receiving the callback, optionally overridden per widget.
+#[derive(Debug, Clone)]
+pub struct EventMap {
+    defaults: HashMap<String, String>,
+    overrides: HashMap<(String, String), String>,
+}
+
+impl Default for EventMap {
+    fn default() -> Self {
+        let mut events = EventMap {
+            defaults: HashMap::new(),
+            overrides: HashMap::new(),
+        };
+
+        events.insert("tap", "onTap");
+        events.insert("longPress", "onLongPress");
+        events.insert("change", "onChanged");
+        events.insert("submit", "onSubmitted");
+        for button in BUTTON_WIDGETS {
+            events.insert_for(button, "tap", "onPressed");
+        }
+
+        events
+    }
+}
+
+impl EventMap {
+    pub fn insert(&mut self, event: &str, param: &str) {
+        self.defaults.insert(event.to_string(), param.to_string());
+    }
+
+    pub fn insert_for(&mut self, widget: &str, event: &str, param: &str) {
+        self.overrides
+            .insert((widget.to_string(), event.to_string()), param.to_string());
+    }
+
+    pub fn resolve(&self, widget: &str, event: &str) -> Option<&str> {
+        self.overrides
+            .get(&(widget.to_string(), event.to_string()))
+            .or_else(|| self.defaults.get(event))
+            .map(String::as_str)
+    }
+}
+
+#[test]
+fn resolve_tap_per_widget() {
+    let events = EventMap::default();
+
+    assert_eq!(events.resolve("InkWell", "tap"), Some("onTap"));
+    assert_eq!(events.resolve("ElevatedButton", "tap"), Some("onPressed"));
+    assert_eq!(events.resolve("TextField", "change"), Some("onChanged"));
+    assert_eq!(events.resolve("TextField", "hover"), None);
+}
+
+#[test]
+fn overrides_take_precedence_over_defaults() {
+    let mut events = EventMap::default();
+    events.insert_for("Button", "tap", "onPressed");
+
+    assert_eq!(events.resolve("Button", "tap"), Some("onPressed"));
+}
@@ -0,0 +1,236 @@
+use super::{emitter::EmitError, scope::Scope};
+use crate::parser::ast_struct::{AssignOp, BinaryOp, Expr, ExprKind, Span, StringSegment, UnaryOp};
+
+pub fn dart_string(value: &str) -> String {
+    format!("\"{}\"", escape_dart(value))
+}
+
+fn escape_dart(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+        .replace('\t', "\\t")
+        .replace('$', "\\$")
+}
+
+pub fn emit_expr(expr: &Expr, scope: &Scope) -> Result<String, EmitError> {
+    let code = match &expr.kind {
+        ExprKind::String(value) => dart_string(value),
+        ExprKind::Interpolated(segments) => emit_interpolated(segments, scope)?,
+        ExprKind::Number(value) => value.to_string(),
+        ExprKind::Bool(value) => value.to_string(),
+        ExprKind::Null => String::from("null"),
+        ExprKind::Identifier(name) => resolve_identifier(name, expr.span, scope)?,
+        ExprKind::Member(object, member) => format!("{}.{}", emit_expr(object, scope)?, member),
+        ExprKind::Index(object, index) => format!(
+            "{}[{}]",
+            emit_expr(object, scope)?,
+            emit_expr(index, scope)?
+        ),
+        ExprKind::Call(callee, args) => {
+            let args = args
+                .iter()
+                .map(|arg| emit_expr(arg, scope))
+                .collect::<Result<Vec<String>, EmitError>>()?;
+            format!("{}({})", emit_expr(callee, scope)?, args.join(", "))
+        }
+        ExprKind::Unary(UnaryOp::Neg, operand) => match emit_expr(operand, scope)? {
+            // `--a` would be a decrement
+            negated if negated.starts_with('-') => format!("-({})", negated),
+            negated => format!("-{}", negated),
+        },
+        ExprKind::Unary(UnaryOp::Not, operand) => format!("!{}", emit_expr(operand, scope)?),
+        ExprKind::Binary(BinaryOp::Add, _, _) if is_string_concat(expr) => {
+            emit_interpolated(&concat_segments(expr), scope)?
+        }
+        ExprKind::Binary(op, lhs, rhs) => format!(
+            "{} {} {}",
+            emit_expr(lhs, scope)?,
+            binary_op(*op),
+            emit_expr(rhs, scope)?
+        ),
+        ExprKind::Paren(inner) => format!("({})", emit_expr(inner, scope)?),
+        ExprKind::Assign(op, target, value) => {
+            check_assign_target(target, scope)?;
+            format!(
+                "{} {} {}",
+                emit_expr(target, scope)?,
+                assign_op(*op),
+                emit_expr(value, scope)?
+            )
+        }
+    };
+
+    Ok(code)
+}
+
+// Capitalized names are Dart types and constructors (`Colors.red`, `Icons.add`), not bindings.
+fn resolve_identifier(name: &str, span: Span, scope: &Scope) -> Result<String, EmitError> {
+    match name.chars().next() {
+        Some(ch) if ch.is_uppercase() => Ok(name.to_string()),
+        _ => scope.resolve(name, span),
+    }
+}
+
+// `count`, `form.name` or `items[index]`, as long as it starts from a state
+fn check_assign_target(target: &Expr, scope: &Scope) -> Result<(), EmitError> {
+    match &target.kind {
+        ExprKind::Identifier(name) => scope.check_assignable(name, target.span),
+        ExprKind::Member(object, _) | ExprKind::Index(object, _) => {
+            check_assign_target(object, scope)
+        }
+        _ => Err(EmitError::AssignToNonState(target.span, None)),
+    }
+}
+
+fn assign_op(op: AssignOp) -> &'static str {
+    match op {
+        AssignOp::Set => "=",
+        AssignOp::Add => "+=",
+        AssignOp::Sub => "-=",
+        AssignOp::Mul => "*=",
+        AssignOp::Div => "/=",
+    }
+}
+
+fn binary_op(op: BinaryOp) -> &'static str {
+    match op {
+        BinaryOp::Add => "+",
+        BinaryOp::Sub => "-",
+        BinaryOp::Mul => "*",
+        BinaryOp::Div => "/",
+        BinaryOp::Eq => "==",
+        BinaryOp::NotEq => "!=",
+        BinaryOp::Lt => "<",
+        BinaryOp::Gt => ">",
+        BinaryOp::LtEq => "<=",
+        BinaryOp::GtEq => ">=",
+        BinaryOp::And => "&&",
+        BinaryOp::Or => "||",
+    }
+}
+
+// Dart can't `+` a String and an int, so `"Counter: " + count` is turned
+// into the interpolation `"Counter: $count"`.
+fn is_string_concat(expr: &Expr) -> bool {
+    match &expr.kind {
+        ExprKind::String(_) | ExprKind::Interpolated(_) => true,
+        ExprKind::Binary(BinaryOp::Add, lhs, rhs) => is_string_concat(lhs) || is_string_concat(rhs),
+        _ => false,
+    }
+}
+
+fn concat_segments(expr: &Expr) -> Vec<StringSegment> {
+    match &expr.kind {
+        ExprKind::String(value) => vec![StringSegment::Literal(value.clone())],
+        ExprKind::Interpolated(segments) => segments.clone(),
+        ExprKind::Binary(BinaryOp::Add, lhs, rhs) if is_string_concat(expr) => {
+            let mut segments = concat_segments(lhs);
+            segments.append(&mut concat_segments(rhs));
+            segments
+        }
+        _ => vec![StringSegment::Expr(expr.clone())],
+    }
+}
+
+// "Counter: ${controller.counter}", with `$name` for plain identifiers
+fn emit_interpolated(segments: &[StringSegment], scope: &Scope) -> Result<String, EmitError> {
+    let mut out = String::from("\"");
+
+    for (index, segment) in segments.iter().enumerate() {
+        match segment {
+            StringSegment::Literal(value) => out += &escape_dart(value),
+            StringSegment::Expr(Expr {
+                kind: ExprKind::Identifier(name),
+                span,
+            }) if !continues_identifier(segments.get(index + 1)) => {
+                match resolve_identifier(name, *span, scope)? {
+                    resolved if resolved == *name => out += &format!("${}", name),
+                    resolved => out += &format!("${{{}}}", resolved),
+                }
+            }
+            StringSegment::Expr(expr) => out += &format!("${{{}}}", emit_expr(expr, scope)?),
+        }
+    }
+
+    Ok(out + "\"")
+}
+
+fn continues_identifier(next: Option<&StringSegment>) -> bool {
+    match next {
+        Some(StringSegment::Literal(value)) => value
+            .chars()
+            .next()
+            .is_some_and(|ch| ch.is_alphanumeric() || ch == '_'),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+fn emit_source(src: &str) -> String {
+    let tokens = crate::lexer::lexer::lex(src).unwrap();
+    let mut tokens = std::collections::VecDeque::from(tokens);
+    let expr = crate::parser::expression::parse_expr(&mut tokens).unwrap();
+
+    emit_expr(&expr, &Scope::unchecked()).unwrap()
+}
+
+#[test]
+fn escape_dart_strings() {
+    assert_eq!(
+        dart_string("say \"hi\" for $5"),
+        "\"say \\\"hi\\\" for \\$5\""
+    );
+}
+
+#[test]
+fn emit_arithmetic_keeping_parentheses() {
+    assert_eq!(emit_source("-a * (b + 1) / 2"), "-a * (b + 1) / 2");
+}
+
+#[test]
+fn emit_double_negation_apart_from_a_decrement() {
+    assert_eq!(emit_source("- -a"), "-(-a)");
+    assert_eq!(emit_source("-(-a)"), "-(-a)");
+}
+
+#[test]
+fn emit_string_concatenation_as_interpolation() {
+    assert_eq!(
+        emit_source("\"Counter: \" + controller.counter + \"!\""),
+        "\"Counter: ${controller.counter}!\""
+    );
+    assert_eq!(emit_source("1 + 2 + \"x\""), "\"${1 + 2}x\"");
+    assert_eq!(emit_source("\"Hi \" + name"), "\"Hi $name\"");
+}
+
+#[test]
+fn emit_conditions() {
+    assert_eq!(
+        emit_source("!controller.items.isEmpty && (count == 0 || done != true)"),
+        "!controller.items.isEmpty && (count == 0 || done != true)"
+    );
+}
+
+#[test]
+fn emit_assignments_to_states() {
+    let tokens = crate::lexer::lexer::lex("count += step").unwrap();
+    let mut tokens = std::collections::VecDeque::from(tokens);
+    let handler = crate::parser::expression::parse_handler(&mut tokens).unwrap();
+    let scope = Scope::stateful(["step"], ["count"]);
+
+    assert_eq!(emit_expr(&handler, &scope).unwrap(), "count += widget.step");
+    assert!(matches!(
+        emit_expr(&handler, &Scope::stateful(["count"], ["step"])),
+        Err(EmitError::AssignToNonState(_, Some(name))) if name == "count"
+    ));
+}
+
+#[test]
+fn emit_index_and_call() {
+    assert_eq!(
+        emit_source("controller.history[index].toUpperCase()"),
+        "controller.history[index].toUpperCase()"
+    );
+}
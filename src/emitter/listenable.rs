@@ -0,0 +1,209 @@
+use super::{
+    dart_struct::DartExpr,
+    emitter::{item_builder_of, slot_of, EmitError},
+    scope::Scope,
+};
+use crate::{
+    catalog::catalog::Catalog,
+    parser::ast_struct::{Expr, Node, Span, Widget},
+};
+
+// `<Text listen="false">` leaves the widget and everything under it alone, for
+// trees that already rebuild some other way.
+pub const LISTEN_ATTRIBUTE: &str = "listen";
+
+// Flutter's own listenables, with or without type arguments: `ValueNotifier<int>`
+const LISTENABLE_TYPES: [&str; 6] = [
+    "Listenable",
+    "ChangeNotifier",
+    "ValueNotifier",
+    "ValueListenable",
+    "Animation",
+    "AnimationController",
+];
+
+/// Whether widgets reading a param of type `ty` should rebuild when it notifies:
+/// it is one of Flutter's listenables or of the project's own `listenables`, the
+/// Dart classes behind a param being out of sight of the markup. Nullable types
+/// are left out: `ListenableBuilder` needs something to listen to.
+pub fn is_listenable(ty: &str, listenables: &[String]) -> bool {
+    let name = match ty.split_once('<') {
+        Some((name, _)) => name,
+        None => ty,
+    };
+    let nullable = ty.ends_with('?');
+
+    !nullable
+        && (LISTENABLE_TYPES.contains(&name)
+            || listenables.iter().any(|listenable| listenable == name))
+}
+
+/// `false` for `listen="false"`.
+pub fn listens(widget: &Widget) -> Result<bool, EmitError> {
+    let attribute = widget
+        .attributes
+        .iter()
+        .find(|attribute| attribute.name == LISTEN_ATTRIBUTE);
+
+    match attribute.map(|attribute| attribute.value.as_str()) {
+        None | Some("true") => Ok(true),
+        Some("false") => Ok(false),
+        Some(value) => Err(EmitError::InvalidListen(
+            attribute.map_or(widget.span, |attribute| attribute.span),
+            value.to_string(),
+        )),
+    }
+}
+
+/// The listenables read while building `widget` itself: by its content and props,
+/// by the `<if>` and `<for>` among its children, by the count of its lazy builder,
+/// and by the widgets it passes in slots taking something narrower than a `Widget`,
+/// like the `PreferredSizeWidget` of `appBar:`, which a `ListenableBuilder` can't
+/// stand in for. Events only tear off methods, they read nothing.
+pub fn widget_reads(
+    widget: &Widget,
+    catalog: &Catalog,
+    scope: &Scope,
+) -> Result<Vec<String>, EmitError> {
+    let mut reads = Vec::new();
+    if !listens(widget)? {
+        return Ok(reads);
+    }
+
+    let exprs = widget
+        .content
+        .iter()
+        .chain(widget.props.iter().map(|prop| &prop.value));
+    for expr in exprs {
+        reads.extend(expr_reads(expr, scope));
+    }
+    reads.extend(block_reads(&widget.children, scope));
+
+    let spec = catalog.get(&widget.name);
+    for child in &widget.children {
+        // the lazy constructor is given the count, only the items are built later
+        if let Some((_, builder)) = item_builder_of(child, spec) {
+            for prop in &builder.props {
+                reads.extend(expr_reads(&prop.value, scope));
+            }
+            continue;
+        }
+        let (child, slot) = match (child, slot_of(child)) {
+            (Node::Widget(child), Some(slot)) => (child, slot),
+            _ => continue,
+        };
+        let narrow = spec
+            .and_then(|spec| spec.named(slot))
+            .is_some_and(|param| param.ty.trim_end_matches('?') != "Widget");
+        if narrow {
+            reads.extend(widget_reads(child, catalog, scope)?);
+        }
+    }
+
+    Ok(dedup(reads))
+}
+
+/// The listenables read by the conditions, iterables and keys of the `<if>` and
+/// `<for>` among `nodes`, which their parent evaluates while building itself.
+pub fn block_reads(nodes: &[Node], scope: &Scope) -> Vec<String> {
+    let mut reads = Vec::new();
+    for node in nodes {
+        match node {
+            Node::If(node) => {
+                for branch in &node.branches {
+                    reads.extend(
+                        branch
+                            .condition
+                            .iter()
+                            .flat_map(|expr| expr_reads(expr, scope)),
+                    );
+                    reads.extend(block_reads(&branch.children, scope));
+                }
+            }
+            Node::For(node) => {
+                reads.extend(expr_reads(&node.iterable, scope));
+                let scope = scope.with(
+                    [node.item.as_str()]
+                        .into_iter()
+                        .chain(node.index.as_deref()),
+                );
+                reads.extend(node.key.iter().flat_map(|key| expr_reads(key, &scope)));
+                reads.extend(block_reads(&node.children, &scope));
+            }
+            Node::Widget(_) | Node::Text(_) | Node::Error(_) => {}
+        }
+    }
+    dedup(reads)
+}
+
+pub fn expr_reads(expr: &Expr, scope: &Scope) -> Vec<String> {
+    let reads = expr
+        .identifiers()
+        .into_iter()
+        .filter(|name| scope.is_listenable(name))
+        .map(String::from)
+        .collect();
+    dedup(reads)
+}
+
+/// Rebuilds `expr` whenever one of `reads` notifies:
+/// `ListenableBuilder(listenable: controller, builder: (context, child) => ...)`
+pub fn listen(expr: DartExpr, reads: &[String], scope: &Scope) -> Result<DartExpr, EmitError> {
+    let (comments, expr) = match expr {
+        DartExpr::Commented { comments, expr } => (comments, *expr),
+        expr => (Vec::new(), expr),
+    };
+    let listenables = reads
+        .iter()
+        .map(|name| scope.resolve(name, Span::default()))
+        .collect::<Result<Vec<String>, EmitError>>()?;
+    let listenable = match listenables.as_slice() {
+        [] => return Ok(expr.with_comments(comments)),
+        [listenable] => listenable.clone(),
+        _ => format!("Listenable.merge([{}])", listenables.join(", ")),
+    };
+
+    let builder = DartExpr::Closure {
+        params: vec![String::from("context"), String::from("child")],
+        statements: Vec::new(),
+        result: Box::new(expr),
+    };
+    Ok(DartExpr::call("ListenableBuilder")
+        .with_named("listenable", DartExpr::raw(&listenable))
+        .with_named("builder", builder)
+        .with_comments(comments))
+}
+
+fn dedup(names: Vec<String>) -> Vec<String> {
+    let mut unique: Vec<String> = Vec::new();
+    for name in names {
+        if !unique.contains(&name) {
+            unique.push(name);
+        }
+    }
+    unique
+}
+
+#[test]
+fn flutter_and_project_listenables_are_listenable() {
+    let listenables = [String::from("CounterPageController")];
+
+    assert!(is_listenable("CounterPageController", &listenables));
+    assert!(is_listenable("ValueNotifier<int>", &listenables));
+    assert!(is_listenable("Listenable", &[]));
+    assert!(!is_listenable("CounterPageController", &[]));
+    assert!(!is_listenable("CounterPageController?", &listenables));
+    assert!(!is_listenable("StreamController<int>", &listenables));
+    assert!(!is_listenable("String", &listenables));
+}
+
+#[test]
+fn only_listenables_in_scope_are_read() {
+    let tokens = crate::lexer::lexer::lex("controller.count + other.count + count").unwrap();
+    let mut tokens = std::collections::VecDeque::from(tokens);
+    let expr = crate::parser::expression::parse_expr(&mut tokens).unwrap();
+    let scope = Scope::new(["controller", "other", "count"]).listening(["controller", "other"]);
+
+    assert_eq!(expr_reads(&expr, &scope), vec!["controller", "other"]);
+    assert!(expr_reads(&expr, &scope.with(["controller", "other"])).is_empty());
+}
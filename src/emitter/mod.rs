@@ -0,0 +1,8 @@
+pub mod dart_struct;
+#[allow(clippy::module_inception)]
+pub mod emitter;
+pub mod events;
+pub mod expression;
+pub mod listenable;
+pub mod scope;
+pub mod style;
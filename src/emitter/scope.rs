@@ -0,0 +1,202 @@
+use super::emitter::EmitError;
+use crate::parser::ast_struct::Span;
+
+// the parameter of the generated `build(BuildContext context)`, for `Theme.of(context)`
+const BUILD_CONTEXT: &str = "context";
+
+// lowercase names every generated file sees: `double.infinity`, `int.parse(text)`,
+// and the top-level functions of `dart:core` and of the Flutter import
+const DART_GLOBALS: [&str; 17] = [
+    "bool",
+    "double",
+    "int",
+    "num",
+    "print",
+    "identical",
+    "identityHashCode",
+    "debugPrint",
+    "showAboutDialog",
+    "showDatePicker",
+    "showDialog",
+    "showGeneralDialog",
+    "showLicensePage",
+    "showMenu",
+    "showModalBottomSheet",
+    "showSearch",
+    "showTimePicker",
+];
+
+/// Names an expression inside a widget declaration may refer to. Bare markup
+/// outside of any `widget` declaration has no known scope and is not checked.
+#[derive(Debug, Clone, Default)]
+pub struct Scope {
+    names: Option<Vec<String>>,
+    // the params of a stateful widget, read through `widget.` from its `State`
+    fields: Vec<String>,
+    // the only names an event handler may assign to
+    states: Vec<String>,
+    // params whose changes the widgets reading them must rebuild on, unless a
+    // `ListenableBuilder` above already does
+    listenables: Vec<String>,
+}
+
+impl Scope {
+    pub fn unchecked() -> Self {
+        Scope::default()
+    }
+
+    pub fn new<'a>(names: impl IntoIterator<Item = &'a str>) -> Self {
+        let names = [BUILD_CONTEXT].into_iter().chain(names);
+        Scope {
+            names: Some(names.map(String::from).collect()),
+            ..Scope::default()
+        }
+    }
+
+    /// The scope of the `build()` of a `State`, seeing the params of its widget
+    /// and its own states.
+    pub fn stateful<'a>(
+        params: impl IntoIterator<Item = &'a str>,
+        states: impl IntoIterator<Item = &'a str>,
+    ) -> Self {
+        let fields: Vec<String> = params.into_iter().map(String::from).collect();
+        let states: Vec<String> = states.into_iter().map(String::from).collect();
+
+        let names = [BUILD_CONTEXT.to_string()]
+            .into_iter()
+            .chain(fields.iter().chain(&states).cloned());
+        Scope {
+            names: Some(names.collect()),
+            fields,
+            states,
+            listenables: Vec::new(),
+        }
+    }
+
+    /// The same scope, taking any name to be bound, for files importing Dart ones
+    /// whose top-level names the compiler can't see.
+    pub fn unchecked_names(&self) -> Self {
+        Scope {
+            names: None,
+            ..self.clone()
+        }
+    }
+
+    /// The same scope, with `names` as the listenables left to rebuild on.
+    pub fn listening<'a>(&self, names: impl IntoIterator<Item = &'a str>) -> Self {
+        Scope {
+            listenables: names.into_iter().map(String::from).collect(),
+            ..self.clone()
+        }
+    }
+
+    pub fn listenables(&self) -> impl Iterator<Item = &str> {
+        self.listenables.iter().map(String::as_str)
+    }
+
+    pub fn is_listenable(&self, name: &str) -> bool {
+        self.listenables.iter().any(|listenable| listenable == name)
+    }
+
+    // the new names shadow the params and states of the same name
+    pub fn with<'a>(&self, names: impl IntoIterator<Item = &'a str>) -> Self {
+        let names: Vec<String> = names.into_iter().map(String::from).collect();
+        let outer = |existing: &Vec<String>| -> Vec<String> {
+            existing
+                .iter()
+                .filter(|name| !names.contains(name))
+                .cloned()
+                .collect()
+        };
+
+        Scope {
+            names: self.names.as_ref().map(|existing| {
+                let mut existing = existing.clone();
+                existing.extend(names.iter().cloned());
+                existing
+            }),
+            fields: outer(&self.fields),
+            states: outer(&self.states),
+            listenables: outer(&self.listenables),
+        }
+    }
+
+    pub fn check(&self, name: &str, span: Span) -> Result<(), EmitError> {
+        match &self.names {
+            Some(names)
+                if !names.iter().any(|bound| bound == name) && !DART_GLOBALS.contains(&name) =>
+            {
+                Err(EmitError::UnboundIdentifier(span, name.to_string()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// How `name` is written in Dart: `widget.name` for the params of a stateful widget.
+    pub fn resolve(&self, name: &str, span: Span) -> Result<String, EmitError> {
+        self.check(name, span)?;
+
+        match self.fields.iter().any(|field| field == name) {
+            true => Ok(format!("widget.{}", name)),
+            false => Ok(name.to_string()),
+        }
+    }
+
+    pub fn check_assignable(&self, name: &str, span: Span) -> Result<(), EmitError> {
+        match self.states.iter().any(|state| state == name) {
+            true => Ok(()),
+            false => Err(EmitError::AssignToNonState(span, Some(name.to_string()))),
+        }
+    }
+}
+
+#[test]
+fn unchecked_scope_accepts_anything() {
+    assert!(Scope::unchecked()
+        .check("controller", Span::default())
+        .is_ok());
+}
+
+#[test]
+fn nested_scope_sees_outer_names() {
+    let scope = Scope::new(["controller"]).with(["index"]);
+
+    assert!(scope.check("controller", Span::default()).is_ok());
+    assert!(scope.check("index", Span::default()).is_ok());
+    assert!(scope.check("count", Span::default()).is_err());
+}
+
+#[test]
+fn build_context_is_always_bound() {
+    assert!(Scope::new([]).check("context", Span::default()).is_ok());
+    assert_eq!(
+        Scope::stateful(["count"], [])
+            .resolve("context", Span::default())
+            .unwrap(),
+        "context"
+    );
+}
+
+#[test]
+fn stateful_scope_reads_params_through_the_widget() {
+    let scope = Scope::stateful(["step"], ["count"]);
+
+    assert_eq!(
+        scope.resolve("step", Span::default()).unwrap(),
+        "widget.step"
+    );
+    assert_eq!(scope.resolve("count", Span::default()).unwrap(), "count");
+    assert!(scope.check_assignable("count", Span::default()).is_ok());
+    assert!(scope.check_assignable("step", Span::default()).is_err());
+}
+
+#[test]
+fn loop_bindings_shadow_params_and_states() {
+    let scope = Scope::stateful(["step", "controller"], ["count"])
+        .listening(["controller"])
+        .with(["step", "count", "controller"]);
+
+    assert_eq!(scope.resolve("step", Span::default()).unwrap(), "step");
+    assert!(scope.check_assignable("count", Span::default()).is_err());
+    assert!(!scope.is_listenable("controller"));
+}
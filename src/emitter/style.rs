@@ -0,0 +1,436 @@
+use super::{dart_struct::DartExpr, emitter::EmitError};
+use crate::{guard_clause, parser::ast_struct::StyleProp};
+use std::collections::BTreeMap;
+
+/// Project colors usable wherever a style takes one, `bg:brand`, by name. They map
+/// to the Dart expression of the color.
+pub type Palette = BTreeMap<String, String>;
+
+#[derive(Debug, Clone, Copy)]
+enum ValueKind {
+    Color,
+    Number,
+    Alignment,
+    // the indexes in `SIDES` of the sides it pads
+    Insets(&'static [usize]),
+}
+
+// in the order `EdgeInsets.only` takes them
+const SIDES: [&str; 4] = ["left", "top", "right", "bottom"];
+const ALL_SIDES: &[usize] = &[0, 1, 2, 3];
+const HORIZONTAL: &[usize] = &[0, 2];
+const VERTICAL: &[usize] = &[1, 3];
+
+struct StyleRule {
+    key: &'static str,
+    widget: &'static str,
+    param: &'static str,
+    value: ValueKind,
+}
+
+const fn rule(
+    key: &'static str,
+    widget: &'static str,
+    param: &'static str,
+    value: ValueKind,
+) -> StyleRule {
+    StyleRule {
+        key,
+        widget,
+        param,
+        value,
+    }
+}
+
+// Tailwind-like shorthands, in wrapping order: the first rule wraps the widget
+// itself, the last one ends up outermost. Within a padding group, a later rule
+// is more specific and overrides the sides an earlier one gave: `[p:4 pt:1]`.
+const STYLE_RULES: [StyleRule; 18] = [
+    rule("p", "Padding", "padding", ValueKind::Insets(ALL_SIDES)),
+    rule("px", "Padding", "padding", ValueKind::Insets(HORIZONTAL)),
+    rule("py", "Padding", "padding", ValueKind::Insets(VERTICAL)),
+    rule("pt", "Padding", "padding", ValueKind::Insets(&[1])),
+    rule("pr", "Padding", "padding", ValueKind::Insets(&[2])),
+    rule("pb", "Padding", "padding", ValueKind::Insets(&[3])),
+    rule("pl", "Padding", "padding", ValueKind::Insets(&[0])),
+    rule("w", "SizedBox", "width", ValueKind::Number),
+    rule("h", "SizedBox", "height", ValueKind::Number),
+    rule("bg", "Container", "color", ValueKind::Color),
+    rule("align", "Align", "alignment", ValueKind::Alignment),
+    rule("m", "Padding", "padding", ValueKind::Insets(ALL_SIDES)),
+    rule("mx", "Padding", "padding", ValueKind::Insets(HORIZONTAL)),
+    rule("my", "Padding", "padding", ValueKind::Insets(VERTICAL)),
+    rule("mt", "Padding", "padding", ValueKind::Insets(&[1])),
+    rule("mr", "Padding", "padding", ValueKind::Insets(&[2])),
+    rule("mb", "Padding", "padding", ValueKind::Insets(&[3])),
+    rule("ml", "Padding", "padding", ValueKind::Insets(&[0])),
+];
+
+/// Where `key` comes in the wrapping order, which is also the order `wdart fmt`
+/// lays style blocks out in, since the order they are written in doesn't matter.
+pub fn style_rank(key: &str) -> Option<usize> {
+    STYLE_RULES.iter().position(|rule| rule.key == key)
+}
+
+const NAMED_COLORS: [&str; 3] = ["white", "black", "transparent"];
+
+// the shades of a Material swatch, `red-500`, and of its accent swatch, `red-A200`
+const SHADES: [&str; 10] = [
+    "50", "100", "200", "300", "400", "500", "600", "700", "800", "900",
+];
+const ACCENT_SHADES: [&str; 4] = ["A100", "A200", "A400", "A700"];
+
+// the wrapper being built, so that `w` and `h` share one SizedBox and all the
+// padding keys of a group one Padding
+enum Wrapper<'a> {
+    Call(&'a str, Vec<(&'a str, DartExpr)>),
+    // the first letter of the group's keys, `p` or `m`, and the value of each side
+    Padding(char, [Option<String>; 4]),
+}
+
+impl Wrapper<'_> {
+    fn wrap(self, child: DartExpr) -> DartExpr {
+        let wrapper = match self {
+            Wrapper::Call(widget, args) => args
+                .into_iter()
+                .fold(DartExpr::call(widget), |call, (name, value)| {
+                    call.with_named(name, value)
+                }),
+            Wrapper::Padding(_, sides) => {
+                DartExpr::call("Padding").with_named("padding", insets(&sides))
+            }
+        };
+
+        wrapper.with_named("child", child)
+    }
+}
+
+/// Wraps a widget in the Flutter widgets its `[key:value]` style block expands to,
+/// e.g. `<Column[p:10 bg:red]>` becomes `Container(color: ..., child: Padding(child: Column()))`.
+pub fn apply_style(
+    widget: DartExpr,
+    style: &[StyleProp],
+    palette: &Palette,
+) -> Result<DartExpr, EmitError> {
+    if let Some(unknown) = style
+        .iter()
+        .find(|prop| !STYLE_RULES.iter().any(|rule| rule.key == prop.key))
+    {
+        return Err(EmitError::UnknownStyle(unknown.span, unknown.key.clone()));
+    }
+    for (index, prop) in style.iter().enumerate() {
+        if let Some(first) = style[..index].iter().find(|first| first.key == prop.key) {
+            return Err(EmitError::DuplicateStyle(
+                prop.span,
+                prop.key.clone(),
+                first.span,
+            ));
+        }
+    }
+
+    let mut result = widget;
+    let mut wrapper: Option<Wrapper> = None;
+
+    for rule in &STYLE_RULES {
+        let prop = match style.iter().find(|prop| prop.key == rule.key) {
+            Some(prop) => prop,
+            None => continue,
+        };
+        let invalid =
+            || EmitError::InvalidStyleValue(prop.span, prop.key.clone(), prop.value.clone());
+
+        if let ValueKind::Insets(sides) = rule.value {
+            let value = number(&prop.value).ok_or_else(invalid)?;
+            let group = rule.key.chars().next().unwrap_or_default();
+            match &mut wrapper {
+                Some(Wrapper::Padding(current, given)) if *current == group => {
+                    sides
+                        .iter()
+                        .for_each(|side| given[*side] = Some(value.clone()));
+                }
+                _ => {
+                    if let Some(done) = wrapper.take() {
+                        result = done.wrap(result);
+                    }
+                    let mut given: [Option<String>; 4] = Default::default();
+                    sides
+                        .iter()
+                        .for_each(|side| given[*side] = Some(value.clone()));
+                    wrapper = Some(Wrapper::Padding(group, given));
+                }
+            }
+            continue;
+        }
+
+        let value = style_value(rule.value, &prop.value, palette).ok_or_else(invalid)?;
+        match &mut wrapper {
+            Some(Wrapper::Call(widget, args))
+                if *widget == rule.widget && args.iter().all(|(name, _)| *name != rule.param) =>
+            {
+                args.push((rule.param, value));
+            }
+            _ => {
+                if let Some(done) = wrapper.take() {
+                    result = done.wrap(result);
+                }
+                wrapper = Some(Wrapper::Call(rule.widget, vec![(rule.param, value)]));
+            }
+        }
+    }
+
+    if let Some(done) = wrapper {
+        result = done.wrap(result);
+    }
+
+    Ok(result)
+}
+
+// the shortest `EdgeInsets` padding the sides, in order left, top, right and bottom:
+// `EdgeInsets.all(4)`, `EdgeInsets.symmetric(horizontal: 4)` or `EdgeInsets.only(top: 4)`
+fn insets(sides: &[Option<String>; 4]) -> DartExpr {
+    let [left, top, right, bottom] = sides;
+    let raw = |value: &String| DartExpr::raw(value);
+
+    if let Some(all) = left
+        .as_ref()
+        .filter(|_| sides.iter().all(|side| side == left))
+    {
+        return DartExpr::call("EdgeInsets.all").with_positional(raw(all));
+    }
+    if left == right && top == bottom {
+        let mut call = DartExpr::call("EdgeInsets.symmetric");
+        if let Some(horizontal) = left {
+            call = call.with_named("horizontal", raw(horizontal));
+        }
+        if let Some(vertical) = top {
+            call = call.with_named("vertical", raw(vertical));
+        }
+        return call;
+    }
+
+    SIDES
+        .iter()
+        .zip(sides)
+        .filter_map(|(name, value)| Some((name, value.as_ref()?)))
+        .fold(DartExpr::call("EdgeInsets.only"), |call, (name, value)| {
+            call.with_named(name, raw(value))
+        })
+}
+
+fn style_value(kind: ValueKind, value: &str, palette: &Palette) -> Option<DartExpr> {
+    let expr = match kind {
+        ValueKind::Color => match palette.get(value) {
+            Some(color) => DartExpr::raw(color),
+            None => DartExpr::raw(&color(value)?),
+        },
+        ValueKind::Number | ValueKind::Insets(_) => DartExpr::raw(&number(value)?),
+        ValueKind::Alignment => DartExpr::raw(&format!("Alignment.{}", camel_case(value)?)),
+    };
+
+    Some(expr)
+}
+
+// yellow-100 => Colors.yellow.shade100, light-blue-A200 => Colors.lightBlueAccent.shade200,
+// deep-purple => Colors.deepPurple, #ff8800 => Color(0xFFFF8800)
+pub fn color(value: &str) -> Option<String> {
+    if let Some(hex) = value.strip_prefix('#') {
+        guard_clause!(
+            hex.len() != 6 || !hex.chars().all(|ch| ch.is_ascii_hexdigit()),
+            None
+        );
+        return Some(format!("Color(0xFF{})", hex.to_uppercase()));
+    }
+
+    // black, white and transparent have no shades
+    let swatch = |name: &str| match NAMED_COLORS.contains(&name) {
+        true => None,
+        false => camel_case(name),
+    };
+    match value.rsplit_once('-') {
+        Some((name, shade)) if SHADES.contains(&shade) => {
+            Some(format!("Colors.{}.shade{}", swatch(name)?, shade))
+        }
+        Some((name, shade)) if ACCENT_SHADES.contains(&shade) => Some(format!(
+            "Colors.{}Accent.shade{}",
+            swatch(name)?,
+            &shade[1..]
+        )),
+        // a shade Material doesn't have, like `red-1000`
+        Some((_, shade)) if shade.chars().any(|ch| ch.is_ascii_digit()) => None,
+        _ if NAMED_COLORS.contains(&value) => Some(format!("Colors.{}", value)),
+        _ => Some(format!("Colors.{}", camel_case(value)?)),
+    }
+}
+
+// 10 or 12.5, written the way Dart reads it back as the same finite double. Every
+// number a style takes is a size or a padding, which Flutter asserts aren't negative.
+fn number(value: &str) -> Option<String> {
+    let (whole, fraction) = value.split_once('.').unwrap_or((value, "0"));
+    let digits = |part: &str| !part.is_empty() && part.chars().all(|ch| ch.is_ascii_digit());
+    guard_clause!(!digits(whole) || !digits(fraction), None);
+
+    let parsed = value.parse::<f64>().ok()?;
+    guard_clause!(!parsed.is_finite(), None);
+    Some(value.to_string())
+}
+
+// top-left => topLeft
+fn camel_case(value: &str) -> Option<String> {
+    let mut words = value.split('-');
+    let mut result = words.next()?.to_string();
+
+    for word in words {
+        let mut chars = word.chars();
+        result += &chars.next()?.to_uppercase().to_string();
+        result += chars.as_str();
+    }
+
+    guard_clause!(!result.chars().all(|ch| ch.is_alphanumeric()), None);
+    Some(result)
+}
+
+#[cfg(test)]
+fn prop(key: &str, value: &str) -> StyleProp {
+    StyleProp {
+        key: key.to_string(),
+        value: value.to_string(),
+        span: Default::default(),
+    }
+}
+
+#[test]
+fn map_tailwind_colors_to_material_shades() {
+    assert_eq!(color("yellow-100").unwrap(), "Colors.yellow.shade100");
+    assert_eq!(color("light-blue-50").unwrap(), "Colors.lightBlue.shade50");
+    assert_eq!(color("blue-grey-100").unwrap(), "Colors.blueGrey.shade100");
+    assert_eq!(color("deep-purple").unwrap(), "Colors.deepPurple");
+    assert_eq!(color("red-A200").unwrap(), "Colors.redAccent.shade200");
+    assert_eq!(color("red-1000"), None);
+    assert_eq!(color("red-A300"), None);
+    assert_eq!(color("white-100"), None);
+    assert_eq!(color("white").unwrap(), "Colors.white");
+    assert_eq!(color("#ff8800").unwrap(), "Color(0xFFFF8800)");
+}
+
+#[test]
+fn map_hyphenated_alignment_to_camel_case() {
+    let expr = style_value(ValueKind::Alignment, "top-left", &Palette::new()).unwrap();
+
+    assert_eq!(expr.render(0), "Alignment.topLeft");
+}
+
+#[test]
+fn merge_width_and_height_into_one_sized_box() {
+    let style = vec![prop("h", "20"), prop("w", "10")];
+    let expr = apply_style(DartExpr::call("Icon"), &style, &Palette::new()).unwrap();
+    let should_be = "\
+SizedBox(
+  width: 10,
+  height: 20,
+  child: Icon(),
+)";
+
+    pretty_assertions::assert_eq!(expr.render(0), should_be);
+}
+
+#[test]
+fn merge_sides_into_one_padding() {
+    let style = vec![
+        prop("pt", "4"),
+        prop("pb", "8"),
+        prop("px", "2"),
+        prop("py", "6"),
+    ];
+    let expr = apply_style(DartExpr::call("Icon"), &style, &Palette::new()).unwrap();
+    let should_be = "\
+Padding(
+  padding: EdgeInsets.only(left: 2, top: 4, right: 2, bottom: 8),
+  child: Icon(),
+)";
+
+    pretty_assertions::assert_eq!(expr.render(0), should_be);
+}
+
+#[test]
+fn shortest_insets_pad_the_sides() {
+    let render = |style: &[StyleProp]| {
+        apply_style(DartExpr::call("Icon"), style, &Palette::new())
+            .unwrap()
+            .render(0)
+    };
+
+    assert!(render(&[prop("px", "4"), prop("py", "4")]).contains("EdgeInsets.all(4)"));
+    assert!(render(&[prop("px", "4")]).contains("EdgeInsets.symmetric(horizontal: 4)"));
+    assert!(render(&[prop("p", "4"), prop("pt", "1")])
+        .contains("EdgeInsets.only(left: 4, top: 1, right: 4, bottom: 4)"));
+    // paddings inside and outside the background stay apart
+    assert_eq!(
+        render(&[prop("p", "4"), prop("m", "8")])
+            .matches("Padding(")
+            .count(),
+        2
+    );
+}
+
+#[test]
+fn negative_sizes_are_an_error() {
+    for key in ["p", "w", "mt"] {
+        let got = apply_style(DartExpr::call("Icon"), &[prop(key, "-1")], &Palette::new());
+
+        assert!(
+            matches!(&got, Err(EmitError::InvalidStyleValue(..))),
+            "{:?} should be an error",
+            got
+        );
+    }
+}
+
+#[test]
+fn numbers_are_finite_decimals() {
+    assert_eq!(number("12.5").as_deref(), Some("12.5"));
+    for value in [
+        "-4",
+        "inf",
+        "NaN",
+        "1e400",
+        "1e3",
+        "4.",
+        ".5",
+        "1".repeat(400).as_str(),
+    ] {
+        assert_eq!(number(value), None, "{}", value);
+    }
+}
+
+#[test]
+fn repeated_style_key_is_an_error() {
+    let style = vec![prop("p", "4"), prop("p", "8")];
+    let got = apply_style(DartExpr::call("Icon"), &style, &Palette::new());
+
+    assert!(
+        matches!(&got, Err(EmitError::DuplicateStyle(_, key, _)) if key == "p"),
+        "{:?} should be a duplicate",
+        got
+    );
+}
+
+#[test]
+fn unknown_style_key_is_an_error() {
+    let got = apply_style(
+        DartExpr::call("Icon"),
+        &[prop("glow", "1")],
+        &Palette::new(),
+    );
+
+    assert!(got.is_err(), "{:?} should be an error", got);
+}
+
+#[test]
+fn palette_colors_come_before_material_ones() {
+    let mut palette = Palette::new();
+    palette.insert(String::from("red"), String::from("Color(0xFFE53935)"));
+    let style = vec![prop("bg", "red")];
+    let expr = apply_style(DartExpr::call("Icon"), &style, &palette).unwrap();
+
+    assert!(expr.render(0).contains("color: Color(0xFFE53935)"));
+}
@@ -0,0 +1,326 @@
+use super::syntax::{SyntaxLine, SyntaxToken, SyntaxTree, Trivia};
+use crate::{
+    emitter::style::style_rank,
+    guard_clause,
+    lexer::token_struct::{Token, TokenKind},
+};
+
+#[derive(Debug, Clone)]
+pub struct FormatOptions {
+    // spaces per indentation level
+    pub indent_width: usize,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions { indent_width: 2 }
+    }
+}
+
+/// Lays out `source`, which must parse, the canonical way: `indent_width` spaces
+/// per level, single spaces between tokens, at most one blank line in a row,
+/// style keys in wrapping order and double-quoted strings. Comments are kept
+/// where they are, re-indented with the line they come before.
+pub fn format(source: &str, tokens: Vec<Token>, options: &FormatOptions) -> String {
+    let tree = SyntaxTree::new(source, tokens);
+    let mut printer = Printer {
+        out: String::new(),
+        pending: Vec::new(),
+        options,
+    };
+
+    printer.lines(&tree.lines, 0);
+    printer.pending.extend(tree.trailing);
+    printer.flush(0, false);
+    printer.out
+}
+
+struct Printer<'a> {
+    out: String,
+    // the trivia seen since the last line was printed, written before the next one
+    pending: Vec<Trivia>,
+    options: &'a FormatOptions,
+}
+
+impl Printer<'_> {
+    fn lines(&mut self, lines: &[SyntaxLine], depth: usize) {
+        for line in lines {
+            self.line(&line.tokens, depth);
+
+            if let Some(block) = &line.block {
+                self.pending.extend(block.indent.leading.iter().cloned());
+                self.lines(&block.lines, depth + 1);
+                if let Some(dedent) = &block.dedent {
+                    self.pending.extend(dedent.leading.iter().cloned());
+                }
+            }
+        }
+    }
+
+    fn line(&mut self, tokens: &[SyntaxToken], depth: usize) {
+        let (first, rest) = match tokens.split_first() {
+            Some(split) => split,
+            None => return,
+        };
+        self.pending.extend(first.leading.iter().cloned());
+        // the line break ending a file of nothing but comments
+        if first.token.kind == TokenKind::Newline && rest.is_empty() {
+            return;
+        }
+        self.flush(depth, true);
+        self.out += &self.pad(depth);
+
+        let mut index = 0;
+        while index < tokens.len() {
+            let token = &tokens[index];
+            if index > 0 {
+                let ends_line = token.token.kind == TokenKind::Newline;
+                self.inline_trivia(&token.leading, ends_line);
+            }
+
+            if let Some((style, len)) = style_block(tokens, index) {
+                self.out += &style;
+                index += len;
+                continue;
+            }
+
+            match &token.token.kind {
+                TokenKind::Newline => self.out.push('\n'),
+                TokenKind::QuotedString(_) | TokenKind::InterpolatedString(_) => {
+                    self.out += &double_quoted(&token.text)
+                }
+                _ => self.out += &token.text,
+            }
+            index += 1;
+        }
+    }
+
+    // Comments between tokens stay on the line, whitespace becomes a single space,
+    // except at the end of the line.
+    fn inline_trivia(&mut self, trivia: &[Trivia], ends_line: bool) {
+        let mut spaced = false;
+        for piece in trivia {
+            if let Trivia::Comment(comment) = piece {
+                self.out.push(' ');
+                self.out += &comment.source();
+            }
+            spaced = true;
+        }
+
+        if spaced && !ends_line {
+            self.out.push(' ');
+        }
+    }
+
+    // Writes the pending comments on lines of their own at `depth`, keeping one
+    // blank line where there were some, except at the very start and end of the file.
+    fn flush(&mut self, depth: usize, before_line: bool) {
+        let pad = self.pad(depth);
+        let mut line_empty = true;
+        let mut blank = false;
+
+        for piece in std::mem::take(&mut self.pending) {
+            match piece {
+                Trivia::Whitespace(_) => {}
+                Trivia::LineBreak if line_empty => blank = true,
+                Trivia::LineBreak => {
+                    self.out.push('\n');
+                    line_empty = true;
+                }
+                Trivia::Comment(comment) => {
+                    if blank && !self.out.is_empty() {
+                        self.out.push('\n');
+                    }
+                    blank = false;
+                    if line_empty {
+                        self.out += &pad;
+                    } else {
+                        self.out.push(' ');
+                    }
+                    self.out += &comment.source();
+                    line_empty = false;
+                }
+            }
+        }
+
+        if !line_empty {
+            self.out.push('\n');
+        }
+        if blank && before_line && !self.out.is_empty() {
+            self.out.push('\n');
+        }
+    }
+
+    fn pad(&self, depth: usize) -> String {
+        " ".repeat(self.options.indent_width * depth)
+    }
+}
+
+// `[p:10 bg:red]` after `<Name`, with its keys in the order they wrap the widget.
+// Returns the block and how many tokens it spans, or `None` to print the tokens as
+// they are when there is no such block or comments inside it.
+fn style_block(tokens: &[SyntaxToken], index: usize) -> Option<(String, usize)> {
+    let kind = |index: usize| tokens.get(index).map(|token| &token.token.kind);
+    guard_clause!(kind(index) != Some(&TokenKind::OpenSquare), None);
+    guard_clause!(
+        index < 2 || kind(index - 2) != Some(&TokenKind::LessThan),
+        None
+    );
+
+    let len = tokens[index..]
+        .iter()
+        .position(|token| token.token.kind == TokenKind::CloseSquare)?
+        + 1;
+    let inside = &tokens[index + 1..index + len - 1];
+    guard_clause!(
+        inside.iter().any(|token| token
+            .leading
+            .iter()
+            .any(|piece| matches!(piece, Trivia::Comment(_)))),
+        None
+    );
+
+    let mut props = Vec::new();
+    for prop in inside.chunks(3) {
+        match prop {
+            [key, colon, value]
+                if matches!(key.token.kind, TokenKind::Identifier(_))
+                    && colon.token.kind == TokenKind::Colon
+                    && matches!(value.token.kind, TokenKind::StyleValue(_)) =>
+            {
+                props.push((key.text.as_str(), value.text.as_str()))
+            }
+            _ => return None,
+        }
+    }
+    // unknown keys last, the emitter reports them anyway
+    props.sort_by_key(|(key, _)| style_rank(key).unwrap_or(usize::MAX));
+
+    let props: Vec<String> = props
+        .iter()
+        .map(|(key, value)| format!("{}:{}", key, value))
+        .collect();
+    Some((format!("[{}]", props.join(" ")), len))
+}
+
+// 'it\'s "ok"' => "it's \"ok\"", leaving what is inside `${...}` alone
+fn double_quoted(text: &str) -> String {
+    let inner = match text
+        .strip_prefix('\'')
+        .and_then(|rest| rest.strip_suffix('\''))
+    {
+        Some(inner) => inner,
+        None => return text.to_string(),
+    };
+
+    let mut out = String::from("\"");
+    let mut chars = inner.chars().peekable();
+    let mut depth = 0;
+    let mut nested_quote: Option<char> = None;
+    while let Some(ch) = chars.next() {
+        match ch {
+            _ if depth > 0 => {
+                match ch {
+                    '"' | '\'' if nested_quote.is_none() => nested_quote = Some(ch),
+                    _ if nested_quote == Some(ch) => nested_quote = None,
+                    '{' if nested_quote.is_none() => depth += 1,
+                    '}' if nested_quote.is_none() => depth -= 1,
+                    _ => {}
+                }
+                out.push(ch);
+            }
+            '\\' => match chars.next() {
+                Some('\'') => out.push('\''),
+                Some(escaped) => {
+                    out.push('\\');
+                    out.push(escaped);
+                }
+                None => out.push('\\'),
+            },
+            '"' => out += "\\\"",
+            '$' if chars.peek() == Some(&'{') => {
+                chars.next();
+                out += "${";
+                depth = 1;
+            }
+            _ => out.push(ch),
+        }
+    }
+
+    out + "\""
+}
+
+#[cfg(test)]
+fn format_source(source: &str) -> String {
+    let tokens = crate::lexer::lexer::lex(source).unwrap();
+    format(source, tokens, &FormatOptions::default())
+}
+
+#[test]
+fn format_indentation_and_spacing() {
+    let source = "\n\n<Column>   \n    <Text>   \"a\"\n\n\n    <Row  @tap:go>\n        'b'\n<Icon>";
+    let should_be = "<Column>\n  <Text> \"a\"\n\n  <Row @tap:go>\n    \"b\"\n<Icon>\n";
+
+    pretty_assertions::assert_eq!(format_source(source), should_be);
+}
+
+#[test]
+fn format_style_keys_in_wrapping_order() {
+    let source = "<Column[bg:red  align:center p:10]>";
+    let should_be = "<Column[p:10 bg:red align:center]>\n";
+
+    pretty_assertions::assert_eq!(format_source(source), should_be);
+}
+
+#[test]
+fn format_keeps_comments_on_their_line() {
+    let source =
+        "// page\n<Column> /* end */\n\n    <!--\n  title -->\n    <Text>   8   // eight\n// bye\n";
+    let should_be =
+        "// page\n<Column> /* end */\n\n  <!--\n  title -->\n  <Text> 8 // eight\n// bye\n";
+
+    pretty_assertions::assert_eq!(format_source(source), should_be);
+}
+
+#[test]
+fn format_a_file_of_only_comments() {
+    let source = "// nothing yet\n\n\n/* to come */";
+    let should_be = "// nothing yet\n\n/* to come */\n";
+
+    pretty_assertions::assert_eq!(format_source(source), should_be);
+}
+
+#[test]
+fn format_single_quotes_as_double_quotes() {
+    assert_eq!(
+        double_quoted(r#"'say "hi", it\'s ${names['x']}'"#),
+        r#""say \"hi\", it's ${names['x']}""#
+    );
+    assert_eq!(double_quoted(r#""kept""#), r#""kept""#);
+}
+
+#[test]
+fn format_examples_idempotently() {
+    let dirs = ["examples", "tests/golden"]
+        .map(|dir| std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join(dir));
+    for dir in dirs {
+        for source in crate::cli::commands::find_sources(&dir).unwrap() {
+            let file = source.path.display().to_string();
+            let text = std::fs::read_to_string(&source.path).unwrap();
+            let formatted = format_source(&text);
+
+            pretty_assertions::assert_eq!(format_source(&formatted), formatted, "{}", file);
+            // the layout changes, not what the markup compiles to
+            let config = Default::default();
+            let compiled = crate::cli::commands::compile(&file, &text, &config);
+            if compiled.is_ok() {
+                assert_eq!(
+                    crate::cli::commands::compile(&file, &formatted, &config),
+                    compiled,
+                    "{}",
+                    file
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,232 @@
+use crate::lexer::token_struct::{Comment, Token, TokenKind};
+use std::{fmt, iter::Peekable, vec::IntoIter};
+
+/// What the lexer skips between two tokens.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Trivia {
+    // spaces, tabs and carriage returns
+    Whitespace(String),
+    LineBreak,
+    Comment(Comment),
+}
+
+/// A token with the source text it was read from and the trivia before it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxToken {
+    pub leading: Vec<Trivia>,
+    pub token: Token,
+    pub text: String,
+}
+
+/// One line of markup, from its first token to its `Newline`, and the lines
+/// indented under it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxLine {
+    pub tokens: Vec<SyntaxToken>,
+    pub block: Option<SyntaxBlock>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxBlock {
+    pub indent: SyntaxToken,
+    pub lines: Vec<SyntaxLine>,
+    // missing only when the tokens end early
+    pub dedent: Option<SyntaxToken>,
+}
+
+/// A lossless tree of a markup file: printing it gives back the source it was
+/// built from, byte for byte, so the formatter can work on it without losing
+/// comments or blank lines.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxTree {
+    pub lines: Vec<SyntaxLine>,
+    // the comments and blank lines after the last token
+    pub trailing: Vec<Trivia>,
+}
+
+impl SyntaxTree {
+    /// Builds the tree of `source` from the tokens `lex` gave for it.
+    pub fn new(source: &str, tokens: Vec<Token>) -> Self {
+        let mut comments: Vec<Comment> = tokens
+            .iter()
+            .flat_map(|token| token.trivia.iter().cloned())
+            .collect();
+        comments.sort_by_key(|comment| comment.span.start);
+
+        let mut previous_end = 0;
+        let mut syntax_tokens = Vec::new();
+        for token in tokens {
+            let leading = split_trivia(source, previous_end, token.start, &comments);
+            previous_end = token.end;
+            syntax_tokens.push(SyntaxToken {
+                leading,
+                text: source[token.start..token.end].to_string(),
+                token,
+            });
+        }
+
+        let mut tokens = syntax_tokens.into_iter().peekable();
+        let lines = build_lines(&mut tokens);
+        SyntaxTree {
+            lines,
+            trailing: split_trivia(source, previous_end, source.len(), &comments),
+        }
+    }
+}
+
+// the trivia in `source[start..end]`, the comments among them are known from the lexer
+fn split_trivia(source: &str, start: usize, end: usize, comments: &[Comment]) -> Vec<Trivia> {
+    let mut trivia = Vec::new();
+    let mut offset = start;
+
+    while offset < end {
+        if let Some(comment) = comments.iter().find(|comment| comment.span.start == offset) {
+            trivia.push(Trivia::Comment(comment.clone()));
+            offset = comment.span.end;
+            continue;
+        }
+
+        let rest = &source[offset..end];
+        if rest.starts_with('\n') {
+            trivia.push(Trivia::LineBreak);
+            offset += 1;
+            continue;
+        }
+
+        let next_stop = comments
+            .iter()
+            .map(|comment| comment.span.start)
+            .filter(|&start| start > offset && start < end)
+            .min()
+            .unwrap_or(end);
+        let len = rest[..next_stop - offset]
+            .find('\n')
+            .unwrap_or(next_stop - offset);
+        trivia.push(Trivia::Whitespace(rest[..len].to_string()));
+        offset += len;
+    }
+
+    trivia
+}
+
+fn build_lines(tokens: &mut Peekable<IntoIter<SyntaxToken>>) -> Vec<SyntaxLine> {
+    let mut lines = Vec::new();
+
+    loop {
+        let line_tokens = match tokens.peek().map(|token| &token.token.kind) {
+            None | Some(TokenKind::Dedent) => return lines,
+            // only at the very start of a file, which the parser rejects anyway
+            Some(TokenKind::Indent) => Vec::new(),
+            Some(_) => {
+                let mut line_tokens = Vec::new();
+                for token in tokens.by_ref() {
+                    let ends_line = token.token.kind == TokenKind::Newline;
+                    line_tokens.push(token);
+                    if ends_line {
+                        break;
+                    }
+                }
+                line_tokens
+            }
+        };
+
+        let block = match tokens.peek().map(|token| &token.token.kind) {
+            Some(TokenKind::Indent) => tokens.next().map(|indent| SyntaxBlock {
+                indent,
+                lines: build_lines(tokens),
+                dedent: tokens.next(),
+            }),
+            _ => None,
+        };
+
+        lines.push(SyntaxLine {
+            tokens: line_tokens,
+            block,
+        });
+    }
+}
+
+impl fmt::Display for Trivia {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Trivia::Whitespace(text) => write!(f, "{}", text),
+            Trivia::LineBreak => writeln!(f),
+            Trivia::Comment(comment) => write!(f, "{}", comment.source()),
+        }
+    }
+}
+
+impl fmt::Display for SyntaxToken {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for trivia in &self.leading {
+            write!(f, "{}", trivia)?;
+        }
+        write!(f, "{}", self.text)
+    }
+}
+
+impl fmt::Display for SyntaxLine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for token in &self.tokens {
+            write!(f, "{}", token)?;
+        }
+        if let Some(block) = &self.block {
+            write!(f, "{}", block.indent)?;
+            for line in &block.lines {
+                write!(f, "{}", line)?;
+            }
+            if let Some(dedent) = &block.dedent {
+                write!(f, "{}", dedent)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for SyntaxTree {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for line in &self.lines {
+            write!(f, "{}", line)?;
+        }
+        for trivia in &self.trailing {
+            write!(f, "{}", trivia)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+fn round_trip(source: &str) -> String {
+    let tokens = crate::lexer::lexer::lex(source).unwrap();
+    SyntaxTree::new(source, tokens).to_string()
+}
+
+#[test]
+fn print_the_source_back_unchanged() {
+    let source = "\n// page\n<Column[p:10  bg:red]> /* end */\n\n  <!--\n title -->\n  <Text>  'x'  // x\n    <A>\n<B>\n\n// end\n  ";
+
+    pretty_assertions::assert_eq!(round_trip(source), source);
+}
+
+#[test]
+fn print_the_examples_back_unchanged() {
+    let dirs = ["examples", "tests/golden"]
+        .map(|dir| std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join(dir));
+    for dir in dirs {
+        for source in crate::cli::commands::find_sources(&dir).unwrap() {
+            let text = std::fs::read_to_string(&source.path).unwrap();
+            pretty_assertions::assert_eq!(round_trip(&text), text, "{}", source.path.display());
+        }
+    }
+}
+
+#[test]
+fn nest_lines_by_indentation() {
+    let source = "<A>\n  <B>\n    <C>\n  <D>\n<E>\n";
+    let tree = SyntaxTree::new(source, crate::lexer::lexer::lex(source).unwrap());
+
+    let children = |line: &SyntaxLine| line.block.as_ref().map_or(0, |block| block.lines.len());
+    assert_eq!(tree.lines.len(), 2);
+    assert_eq!(children(&tree.lines[0]), 2);
+    assert_eq!(children(&tree.lines[0].block.as_ref().unwrap().lines[0]), 1);
+}
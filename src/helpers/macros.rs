@@ -35,3 +35,45 @@ macro_rules! lexer_test {
         }
     };
 }
+
+#[macro_export]
+macro_rules! golden_test {
+    ($name:ident, $file:expr) => {
+        #[cfg(test)]
+        #[test]
+        fn $name() {
+            let src = include_str!(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/golden/",
+                $file,
+                ".flutter"
+            ));
+            let should_be = include_str!(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/golden/",
+                $file,
+                ".dart"
+            ));
+
+            // compiled from where it is, so that its markup imports and the
+            // `wdart.toml` of the goldens apply
+            let file = concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/golden/",
+                $file,
+                ".flutter"
+            );
+            let config =
+                $crate::config::config::Config::discover(std::path::Path::new(file)).unwrap();
+            let got =
+                $crate::cli::commands::compile(file, src, &config).unwrap_or_else(|diagnostics| {
+                    let rendered: Vec<String> = diagnostics
+                        .iter()
+                        .map(|diagnostic| diagnostic.render(src))
+                        .collect();
+                    panic!("{}", rendered.concat())
+                });
+            pretty_assertions::assert_eq!(got, should_be, "Golden file {:?} is out of date", $file);
+        }
+    };
+}
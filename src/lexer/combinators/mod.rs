@@ -1,4 +1,7 @@
 pub mod space_indentation_combinators;
+pub mod take_comment;
 pub mod take_ident;
 pub mod take_number;
+pub mod take_string;
+pub mod take_style;
 pub mod take_while;
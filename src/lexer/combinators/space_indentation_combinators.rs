@@ -1,6 +1,4 @@
 use super::take_while::take_while;
-use crate::lexer::token_struct::TokenKind;
-use anyhow::Result;
 
 pub fn skip_whitespace(input: &str) -> usize {
     let first_char = match input.chars().next() {
@@ -18,15 +16,6 @@ pub fn skip_whitespace(input: &str) -> usize {
     }
 }
 
-pub fn capture_indentation(input: &str) -> Result<(TokenKind, usize)> {
-    let length = match take_while(input, |ch| ch.is_whitespace()) {
-        Ok((_, len_skipped)) => len_skipped,
-        _ => 0,
-    };
-
-    Ok((TokenKind::Indentation(length), length))
-}
-
 trait CharExtension {
     fn is_ws_without_nl(&self) -> bool;
 }
@@ -37,10 +26,11 @@ impl CharExtension for char {
     }
 }
 
+// line breaks are significant, the lexer turns them into Newline/Indent/Dedent
 #[test]
 fn skip_past_several_whitespace_chars() {
     let src = " \t\n\r123";
-    let should_be = 4;
+    let should_be = 2;
 
     let num_skipped = skip_whitespace(src);
     assert_eq!(num_skipped, should_be);
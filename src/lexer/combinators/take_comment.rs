@@ -0,0 +1,71 @@
+use crate::lexer::token_struct::CommentKind;
+use anyhow::{bail, Result};
+
+/// Takes a `// line`, `/* block */` or `<!-- markup -->` comment from the start of
+/// `input`: its kind, its text between the delimiters and the length read. Line
+/// comments stop before the line break. `None` when `input` isn't a comment.
+pub fn tokenize_comment(input: &str) -> Result<Option<(CommentKind, String, usize)>> {
+    if let Some(rest) = input.strip_prefix("//") {
+        let text = rest.split('\n').next().unwrap_or_default();
+        return Ok(Some((CommentKind::Line, text.to_string(), text.len() + 2)));
+    }
+
+    let (kind, open, close) = if input.starts_with("/*") {
+        (CommentKind::Block, "/*", "*/")
+    } else if input.starts_with("<!--") {
+        (CommentKind::Markup, "<!--", "-->")
+    } else {
+        return Ok(None);
+    };
+
+    let rest = &input[open.len()..];
+    match rest.find(close) {
+        Some(end) => Ok(Some((
+            kind,
+            rest[..end].to_string(),
+            open.len() + end + close.len(),
+        ))),
+        None => bail!("unterminated comment, expected `{}`", close),
+    }
+}
+
+#[test]
+fn tokenize_line_comment_up_to_the_line_break() {
+    let got = tokenize_comment("// the title\n<Text>").unwrap();
+
+    assert_eq!(
+        got,
+        Some((CommentKind::Line, String::from(" the title"), 12))
+    );
+}
+
+#[test]
+fn tokenize_block_comment_over_several_lines() {
+    let got = tokenize_comment("/* a\n   b */ <Text>").unwrap();
+
+    assert_eq!(
+        got,
+        Some((CommentKind::Block, String::from(" a\n   b "), 12))
+    );
+}
+
+#[test]
+fn tokenize_markup_comment() {
+    let got = tokenize_comment("<!-- header -->").unwrap();
+
+    assert_eq!(
+        got,
+        Some((CommentKind::Markup, String::from(" header "), 15))
+    );
+}
+
+#[test]
+fn division_and_tags_are_not_comments() {
+    assert_eq!(tokenize_comment("/ 2").unwrap(), None);
+    assert_eq!(tokenize_comment("<Text>").unwrap(), None);
+}
+
+#[test]
+fn unterminated_block_comment_is_an_error() {
+    assert!(tokenize_comment("/* never closed").is_err());
+}
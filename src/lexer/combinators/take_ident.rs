@@ -5,14 +5,22 @@ use std::{io::ErrorKind, str};
 
 pub fn tokenize_ident(input: &str) -> Result<(TokenKind, usize)> {
     match input.chars().next() {
-        Some(ch) if ch.is_digit(10) => bail!("Identifiers cannot start with a digit"),
+        Some(ch) if ch.is_ascii_digit() => bail!("Identifiers cannot start with a digit"),
         None => bail!(ErrorKind::UnexpectedEof),
         _ => {}
     }
 
     let (got, len_read) = take_while(input, |ch| ch.is_alphanumeric() || ch == '_')?;
 
-    let tok = TokenKind::Identifier(got.to_string());
+    let tok = match got {
+        "widget" => TokenKind::WidgetKW,
+        "import" => TokenKind::ImportKW,
+        "if" => TokenKind::IfKW,
+        "else" => TokenKind::ElseKW,
+        "for" => TokenKind::ForKW,
+        "in" => TokenKind::InKW,
+        _ => TokenKind::Identifier(got.to_string()),
+    };
 
     Ok((tok, len_read))
 }
@@ -22,3 +30,12 @@ lexer_test!(tokenize_an_identifer, tokenize_ident, "Foo" => "Foo");
 lexer_test!(tokenize_ident_containing_an_underscore, tokenize_ident, "Foo_bar" => "Foo_bar");
 lexer_test!(FAIL: tokenize_ident_cant_start_with_number, tokenize_ident, "7Foo_bar");
 lexer_test!(FAIL: tokenize_ident_cant_start_with_dot, tokenize_ident, ".Foo_bar");
+lexer_test!(tokenize_widget_keyword, tokenize_ident, "widget" => TokenKind::WidgetKW);
+lexer_test!(tokenize_ident_starting_with_a_keyword, tokenize_ident, "widgets" => "widgets");
+lexer_test!(tokenize_import_keyword, tokenize_ident, "import" => TokenKind::ImportKW);
+lexer_test!(tokenize_if_keyword, tokenize_ident, "if" => TokenKind::IfKW);
+lexer_test!(tokenize_else_keyword, tokenize_ident, "else" => TokenKind::ElseKW);
+lexer_test!(tokenize_for_keyword, tokenize_ident, "for" => TokenKind::ForKW);
+lexer_test!(tokenize_ident_starting_with_in, tokenize_ident, "index" => "index");
+// only a keyword at the start of a declaration's line, which the parser knows
+lexer_test!(tokenize_state_as_an_identifier, tokenize_ident, "state" => "state");
@@ -5,7 +5,7 @@ use anyhow::Result;
 pub fn tokenize_number(input: &str) -> Result<(TokenKind, usize)> {
     let mut dot_seen = false;
     let (got, len_read) = take_while(input, |ch| match ch {
-        c if c.is_digit(10) => true,
+        c if c.is_ascii_digit() => true,
         c if c == '.' && !dot_seen => {
             dot_seen = true;
             true
@@ -0,0 +1,193 @@
+use crate::{
+    lexer::{
+        lexer::LexError,
+        token_struct::{Span, StringPart, TokenKind},
+    },
+    lexer_test,
+};
+use anyhow::{bail, Result};
+use std::{io::ErrorKind, str};
+
+/// Tokenizes a double- or single-quoted string, unescaping `\n`, `\t`, `\"`, `\'`,
+/// `\\` and `\$`.
+/// Strings containing `${expr}` or `$name` become an `InterpolatedString` whose
+/// expression sources are left for the parser.
+pub fn tokenize_string(input: &str) -> Result<(TokenKind, usize)> {
+    let mut chars = input.char_indices().peekable();
+    let quote = match chars.next() {
+        Some((_, quote @ ('"' | '\''))) => quote,
+        Some(_) => bail!(ErrorKind::InvalidInput),
+        None => bail!(ErrorKind::UnexpectedEof),
+    };
+
+    let mut parts = Vec::new();
+    let mut literal = String::new();
+
+    while let Some((index, ch)) = chars.next() {
+        match ch {
+            _ if ch == quote => {
+                if !literal.is_empty() || parts.is_empty() {
+                    parts.push(StringPart::Literal { value: literal });
+                }
+
+                let token = match parts.as_slice() {
+                    [StringPart::Literal { value }] => TokenKind::QuotedString(value.clone()),
+                    _ => TokenKind::InterpolatedString(parts),
+                };
+                return Ok((token, index + 1));
+            }
+            '\\' => {
+                let escaped = match chars.next() {
+                    Some((_, 'n')) => '\n',
+                    Some((_, 't')) => '\t',
+                    Some((_, '"')) => '"',
+                    Some((_, '\'')) => '\'',
+                    Some((_, '\\')) => '\\',
+                    Some((_, '$')) => '$',
+                    Some((_, other)) => {
+                        let message = format!("unknown escape sequence `\\{}`", other);
+                        return Err(error_at(index, 1 + other.len_utf8(), message));
+                    }
+                    None => return Err(unterminated(input.len(), quote)),
+                };
+                literal.push(escaped);
+            }
+            '$' => {
+                let (source, offset) = match chars.peek() {
+                    Some((_, '{')) => {
+                        chars.next();
+                        (take_braced(&mut chars, index, input.len())?, index + 2)
+                    }
+                    Some((_, next)) if next.is_alphabetic() || *next == '_' => {
+                        let mut name = String::new();
+                        while let Some((_, next)) =
+                            chars.next_if(|(_, next)| next.is_alphanumeric() || *next == '_')
+                        {
+                            name.push(next);
+                        }
+                        (name, index + 1)
+                    }
+                    _ => {
+                        let message = String::from("expected an expression after `$`");
+                        return Err(error_at(index, 1, message));
+                    }
+                };
+
+                if !literal.is_empty() {
+                    parts.push(StringPart::Literal {
+                        value: std::mem::take(&mut literal),
+                    });
+                }
+                parts.push(StringPart::Interpolation { source, offset });
+            }
+            '\n' => return Err(unterminated(index, quote)),
+            _ => literal.push(ch),
+        }
+    }
+
+    Err(unterminated(input.len(), quote))
+}
+
+// The source of `${...}` up to its matching brace, skipping braces inside nested
+// strings. `start` is where the `$` is and `end` where the input ends.
+fn take_braced<I>(chars: &mut std::iter::Peekable<I>, start: usize, end: usize) -> Result<String>
+where
+    I: Iterator<Item = (usize, char)>,
+{
+    let mut source = String::new();
+    let mut depth = 0;
+    let mut in_string: Option<char> = None;
+
+    for (index, ch) in chars.by_ref() {
+        match ch {
+            '"' | '\'' if in_string.is_none() => in_string = Some(ch),
+            _ if in_string == Some(ch) => in_string = None,
+            '{' if in_string.is_none() => depth += 1,
+            '}' if in_string.is_none() && depth == 0 => {
+                if source.trim().is_empty() {
+                    let message = String::from("empty interpolation");
+                    return Err(error_at(start, index + 1 - start, message));
+                }
+                return Ok(source);
+            }
+            '}' if in_string.is_none() => depth -= 1,
+            '\n' => {
+                let message = String::from("unterminated interpolation, expected `}`");
+                return Err(error_at(index, 0, message));
+            }
+            _ => {}
+        }
+        source.push(ch);
+    }
+
+    let message = String::from("unterminated interpolation, expected `}`");
+    Err(error_at(end, 0, message))
+}
+
+// where the line or the input ends before the closing quote
+fn unterminated(index: usize, quote: char) -> anyhow::Error {
+    error_at(
+        index,
+        0,
+        format!("unterminated string, expected `{}`", quote),
+    )
+}
+
+// an error spanning `len` bytes from `index`, counted from the opening quote
+fn error_at(index: usize, len: usize, message: String) -> anyhow::Error {
+    LexError {
+        span: Span::new(index, index + len),
+        message,
+    }
+    .into()
+}
+
+lexer_test!(tokenize_plain_string, tokenize_string, "\"Increment\"" => TokenKind::QuotedString(String::from("Increment")));
+lexer_test!(tokenize_empty_string, tokenize_string, "\"\"" => TokenKind::QuotedString(String::new()));
+lexer_test!(tokenize_string_with_escapes, tokenize_string, r#""say \"hi\"\n\\ \$5""# => TokenKind::QuotedString(String::from("say \"hi\"\n\\ $5")));
+lexer_test!(tokenize_string_with_interpolation, tokenize_string, "\"Counter: ${controller.counter}!\"" => TokenKind::InterpolatedString(vec![
+    StringPart::Literal { value: String::from("Counter: ") },
+    StringPart::Interpolation { source: String::from("controller.counter"), offset: 12 },
+    StringPart::Literal { value: String::from("!") },
+]));
+lexer_test!(tokenize_string_with_short_interpolation, tokenize_string, "\"$count items\"" => TokenKind::InterpolatedString(vec![
+    StringPart::Interpolation { source: String::from("count"), offset: 2 },
+    StringPart::Literal { value: String::from(" items") },
+]));
+lexer_test!(tokenize_interpolation_containing_a_string, tokenize_string, "\"${names[\"}\"]}\"" => TokenKind::InterpolatedString(vec![
+    StringPart::Interpolation { source: String::from("names[\"}\"]"), offset: 3 },
+]));
+lexer_test!(tokenize_single_quoted_string, tokenize_string, r#"'say "hi" it\'s $name'"# => TokenKind::InterpolatedString(vec![
+    StringPart::Literal { value: String::from("say \"hi\" it's ") },
+    StringPart::Interpolation { source: String::from("name"), offset: 17 },
+]));
+lexer_test!(FAIL: tokenize_mismatched_quotes, tokenize_string, "'Counter\"");
+lexer_test!(FAIL: tokenize_unterminated_string, tokenize_string, "\"Counter");
+lexer_test!(FAIL: tokenize_unterminated_interpolation, tokenize_string, "\"${controller\"");
+lexer_test!(FAIL: tokenize_unknown_escape, tokenize_string, r#""\q""#);
+
+#[test]
+fn string_errors_point_at_the_offending_character() {
+    let error = |input: &str| -> LexError {
+        tokenize_string(input)
+            .unwrap_err()
+            .downcast::<LexError>()
+            .unwrap()
+    };
+
+    let got = error(r#""a \q b""#);
+    assert_eq!(
+        (got.span, got.message.as_str()),
+        (Span::new(3, 5), "unknown escape sequence `\\q`")
+    );
+    let got = error("\"cost: $ 5\"");
+    assert_eq!(
+        (got.span, got.message.as_str()),
+        (Span::new(7, 8), "expected an expression after `$`")
+    );
+    let got = error("\"abc\n");
+    assert_eq!(
+        (got.span, got.message.as_str()),
+        (Span::new(4, 4), "unterminated string, expected `\"`")
+    );
+}
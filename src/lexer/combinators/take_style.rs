@@ -0,0 +1,31 @@
+use super::take_while::take_while;
+use crate::{lexer::token_struct::TokenKind, lexer_test};
+use anyhow::{bail, Result};
+use std::{io::ErrorKind, str};
+
+/// Tokenizes the inside of a `[key:value ...]` style block, where values such as
+/// `yellow-100` or `#ff0000` are taken as a whole instead of as an expression.
+pub fn tokenize_style(input: &str) -> Result<(TokenKind, usize)> {
+    match input.chars().next() {
+        Some(':') => return Ok((TokenKind::Colon, 1)),
+        Some(']') => return Ok((TokenKind::CloseSquare, 1)),
+        None => bail!(ErrorKind::UnexpectedEof),
+        _ => {}
+    }
+
+    let (got, len_read) = take_while(input, |ch| !ch.is_whitespace() && ch != ':' && ch != ']')?;
+
+    let tok = if input[len_read..].starts_with(':') {
+        TokenKind::Identifier(got.to_string())
+    } else {
+        TokenKind::StyleValue(got.to_string())
+    };
+
+    Ok((tok, len_read))
+}
+
+lexer_test!(tokenize_style_key, tokenize_style, "bg:yellow-100" => "bg");
+lexer_test!(tokenize_hyphenated_style_value, tokenize_style, "yellow-100]" => TokenKind::StyleValue(String::from("yellow-100")));
+lexer_test!(tokenize_numeric_style_value, tokenize_style, "10 align:center" => TokenKind::StyleValue(String::from("10")));
+lexer_test!(tokenize_style_block_end, tokenize_style, "]>" => TokenKind::CloseSquare);
+lexer_test!(FAIL: tokenize_style_cant_start_with_whitespace, tokenize_style, " bg:red");
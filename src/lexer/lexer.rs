@@ -1,15 +1,35 @@
 use super::{
     combinators::{
-        space_indentation_combinators::{capture_indentation, skip_whitespace},
-        take_ident::tokenize_ident,
-        take_number::tokenize_number,
-        take_while::take_while,
+        space_indentation_combinators::skip_whitespace, take_comment::tokenize_comment,
+        take_ident::tokenize_ident, take_number::tokenize_number, take_string::tokenize_string,
+        take_style::tokenize_style,
     },
-    token_struct::{Token, TokenKind},
+    token_struct::{Comment, Span, Token, TokenKind},
 };
 
+use crate::diagnostics::diagnostic::Diagnostic;
 use anyhow::{bail, Result};
-use std::{io::ErrorKind, str};
+use std::{fmt, io::ErrorKind, str};
+
+#[derive(Debug)]
+pub struct LexError {
+    pub span: Span,
+    pub message: String,
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for LexError {}
+
+impl LexError {
+    pub fn to_diagnostic(&self, file: &str) -> Diagnostic {
+        Diagnostic::error(file, self.span, &self.message)
+    }
+}
 
 pub fn tokenize_single_token(input: &str) -> Result<(TokenKind, usize)> {
     let next = match input.chars().next() {
@@ -18,14 +38,27 @@ pub fn tokenize_single_token(input: &str) -> Result<(TokenKind, usize)> {
     };
 
     let (token_got, length) = match next {
+        '*' if input.starts_with("*=") => (TokenKind::AsteriskEquals, 2),
         '*' => (TokenKind::Asterisk, 1),
+        '=' if input.starts_with("==") => (TokenKind::EqualsEquals, 2),
         '=' => (TokenKind::Equals, 1),
+        '!' if input.starts_with("!=") => (TokenKind::NotEquals, 2),
+        '!' => (TokenKind::Bang, 1),
+        '?' => (TokenKind::Question, 1),
+        '&' if input.starts_with("&&") => (TokenKind::AndAnd, 2),
+        '|' if input.starts_with("||") => (TokenKind::OrOr, 2),
+        '+' if input.starts_with("+=") => (TokenKind::PlusEquals, 2),
         '+' => (TokenKind::Plus, 1),
+        '/' if input.starts_with("/=") => (TokenKind::SlashEquals, 2),
         '/' => (TokenKind::Slash, 1),
+        '<' if input.starts_with("<=") => (TokenKind::LessEquals, 2),
         '<' => (TokenKind::LessThan, 1),
+        '>' if input.starts_with(">=") => (TokenKind::GreaterEquals, 2),
         '>' => (TokenKind::GreaterThan, 1),
+        '-' if input.starts_with("-=") => (TokenKind::MinusEquals, 2),
         '-' => (TokenKind::Minus, 1),
         ':' => (TokenKind::Colon, 1),
+        ',' => (TokenKind::Comma, 1),
         '@' => (TokenKind::At, 1),
         '.' => (TokenKind::Dot, 1),
         ')' => (TokenKind::CloseParen, 1),
@@ -34,60 +67,432 @@ pub fn tokenize_single_token(input: &str) -> Result<(TokenKind, usize)> {
         '[' => (TokenKind::OpenSquare, 1),
         ';' => (TokenKind::Semicolon, 1),
         '0'..='9' => tokenize_number(input)?,
-        '"' => {
-            let (got, len_read) = take_while(&input[1..], |ch| ch != '"')?;
-            let token = TokenKind::QuotedString(got.to_string());
-            (token, len_read + 2)
-        }
+        '"' | '\'' => tokenize_string(input)?,
         c @ '_' | c if c.is_alphabetic() => tokenize_ident(input)?,
-        // c if c.is_whitespace() => (_, skip_whitespace(input)),
-        '\n' => capture_indentation(input)?,
-        _ => bail!(ErrorKind::InvalidData), // ErrorKind::UnknownCharacter(other)
+        other => bail!("unknown character {:?}", other),
     };
 
     Ok((token_got, length))
 }
 
-pub fn lex(input: &str) -> Result<Vec<Token>> {
+#[derive(Debug, Clone, Default)]
+pub struct LexOptions {
+    // spaces each indentation level adds, any consistent width when unset
+    pub indent_width: Option<usize>,
+}
+
+// what `lex` carries from one line to the next
+struct Layout {
+    indent_width: Option<usize>,
+    line: usize,
+    // columns of the open indentation levels, the outermost first
+    indents: Vec<usize>,
+    // comments waiting for the next token, they attach to it as trivia
+    comments: Vec<Comment>,
+}
+
+pub fn lex(input: &str) -> Result<Vec<Token>, LexError> {
+    lex_with(input, &LexOptions::default())
+}
+
+pub fn lex_with(input: &str, options: &LexOptions) -> Result<Vec<Token>, LexError> {
     let mut tokens = Vec::new();
-    let mut remaining = input;
-    let mut row = 1;
-    let mut col_start = 1;
-    let mut col_end;
-    let mut is_line_start = true;
+    let mut is_in_style = false;
+    let mut layout = Layout {
+        indent_width: options.indent_width,
+        line: 1,
+        indents: vec![0],
+        comments: Vec::new(),
+    };
 
+    let mut offset = start_line(input, 0, &mut layout, &mut tokens)?;
     loop {
-        if !is_line_start {
-            let ws = skip_whitespace(remaining);
-            col_start += ws;
-            remaining = &remaining[ws..]
-        } else {
-            is_line_start = false;
-        }
+        offset = skip_comments(input, offset, &mut layout)?;
 
-        // TODO: maybe check for any whitespace too?
+        let remaining = &input[offset..];
         if remaining.is_empty() {
             break;
         }
 
-        let (token, len_read) = tokenize_single_token(remaining)?;
+        if remaining.starts_with('\n') {
+            tokens.push(Token::new(
+                TokenKind::Newline,
+                offset,
+                offset + 1,
+                layout.line,
+            ));
+            is_in_style = false;
+            layout.line += 1;
+            offset = start_line(input, offset + 1, &mut layout, &mut tokens)?;
+            continue;
+        }
+
+        let tokenized = if is_in_style {
+            tokenize_style(remaining)
+        } else {
+            tokenize_single_token(remaining)
+        };
+        let (token, len_read) = tokenized.map_err(|err| match err.downcast::<LexError>() {
+            // pointing into the token, from its start
+            Ok(err) => LexError {
+                span: Span::new(offset + err.span.start, offset + err.span.end),
+                message: err.message,
+            },
+            Err(err) => LexError {
+                span: Span::new(
+                    offset,
+                    offset + remaining.chars().next().map_or(0, char::len_utf8),
+                ),
+                message: err.to_string(),
+            },
+        })?;
+
         match token {
-            TokenKind::Indentation(_) => {
-                is_line_start = true;
-                row += 1;
-                col_start = 1;
-                col_end = col_start + len_read;
-            }
-            _ => {
-                col_end = col_start + len_read;
-            }
+            TokenKind::OpenSquare if opens_style(&tokens) => is_in_style = true,
+            TokenKind::CloseSquare => is_in_style = false,
+            _ => {}
         }
 
-        tokens.push(Token::new(token, col_start, col_end, row));
+        let mut token = Token::new(token, offset, offset + len_read, layout.line);
+        token.trivia = std::mem::take(&mut layout.comments);
+        tokens.push(token);
+
+        layout.line += remaining[..len_read].matches('\n').count();
+        offset += len_read;
+    }
 
-        col_start = col_end;
-        remaining = &remaining[len_read..];
+    // the last line may not end with a line break, and every level still open closes
+    let end = input.len();
+    if !matches!(
+        tokens.last(),
+        None | Some(Token {
+            kind: TokenKind::Newline,
+            ..
+        })
+    ) || tokens.is_empty() && !layout.comments.is_empty()
+    {
+        tokens.push(Token::new(TokenKind::Newline, end, end, layout.line));
+    }
+    for _ in 1..layout.indents.len() {
+        tokens.push(Token::new(TokenKind::Dedent, end, end, layout.line));
+    }
+    // comments after the last node have nothing to attach to but the end of the file
+    if let Some(last) = tokens.last_mut() {
+        last.trivia.append(&mut layout.comments);
     }
 
     Ok(tokens)
 }
+
+// Skips the whitespace and comments from `offset`, up to the next token or line
+// break, keeping the comments for the next token.
+fn skip_comments(input: &str, mut offset: usize, layout: &mut Layout) -> Result<usize, LexError> {
+    loop {
+        offset += skip_whitespace(&input[offset..]);
+
+        let remaining = &input[offset..];
+        let comment = tokenize_comment(remaining).map_err(|err| LexError {
+            span: Span::new(offset, offset + 2),
+            message: err.to_string(),
+        })?;
+        let (kind, text, len_read) = match comment {
+            Some(comment) => comment,
+            None => return Ok(offset),
+        };
+
+        layout.comments.push(Comment {
+            kind,
+            text,
+            span: Span::new(offset, offset + len_read),
+        });
+        layout.line += remaining[..len_read].matches('\n').count();
+        offset += len_read;
+    }
+}
+
+// Skips the blank and comment-only lines from `offset`, the start of a line, and
+// compares the indentation of the next one with the open levels. Returns where
+// its content starts.
+fn start_line(
+    input: &str,
+    mut offset: usize,
+    layout: &mut Layout,
+    tokens: &mut Vec<Token>,
+) -> Result<usize, LexError> {
+    let (width, content) = loop {
+        let width = skip_whitespace(&input[offset..]);
+        let content = skip_comments(input, offset, layout)?;
+        match input[content..].chars().next() {
+            Some('\n') => {
+                offset = content + 1;
+                layout.line += 1;
+            }
+            None => return Ok(content),
+            Some(_) => break (width, content),
+        }
+    };
+
+    let current = layout.indents.last().copied().unwrap_or(0);
+    match layout.indent_width {
+        Some(step) if width > current && width != current + step => {
+            return Err(LexError {
+                span: Span::new(offset, offset + width),
+                message: format!(
+                    "indented by {} columns, the project indents by {}",
+                    width - current,
+                    step
+                ),
+            })
+        }
+        _ => {}
+    }
+    if width > current {
+        layout.indents.push(width);
+        let indent = Token::new(TokenKind::Indent, offset, offset + width, layout.line);
+        tokens.push(indent);
+        return Ok(content);
+    }
+
+    while width < layout.indents.last().copied().unwrap_or(0) {
+        layout.indents.pop();
+        let dedent = offset + width;
+        tokens.push(Token::new(TokenKind::Dedent, dedent, dedent, layout.line));
+    }
+    if layout.indents.last() != Some(&width) {
+        return Err(LexError {
+            span: Span::new(offset, offset + width),
+            message: format!(
+                "dedent to column {} does not match any outer indentation level",
+                width
+            ),
+        });
+    }
+
+    Ok(content)
+}
+
+// `<Name[` starts a style block, where `yellow-100` is a single value. Tags start
+// their line, unlike comparisons: `count < items[0]`
+fn opens_style(tokens: &[Token]) -> bool {
+    match tokens {
+        [rest @ .., before, last] => {
+            let line_start = matches!(
+                rest.last().map(|token| &token.kind),
+                None | Some(TokenKind::Newline | TokenKind::Indent | TokenKind::Dedent)
+            );
+            line_start
+                && before.kind == TokenKind::LessThan
+                && matches!(last.kind, TokenKind::Identifier(_))
+        }
+        _ => false,
+    }
+}
+
+#[test]
+fn lex_tracks_byte_offsets_and_lines() {
+    let tokens = lex("<A>\n  <Bé> \"x\"").unwrap();
+    let positions: Vec<(usize, usize, usize)> = tokens
+        .iter()
+        .map(|token| (token.start, token.end, token.line))
+        .collect();
+    let should_be = vec![
+        (0, 1, 1),
+        (1, 2, 1),
+        (2, 3, 1),
+        (3, 4, 1),
+        (4, 6, 2),
+        (6, 7, 2),
+        (7, 10, 2),
+        (10, 11, 2),
+        (12, 15, 2),
+        (15, 15, 2),
+        (15, 15, 2),
+    ];
+
+    pretty_assertions::assert_eq!(positions, should_be);
+}
+
+#[cfg(test)]
+fn layout(src: &str) -> Vec<TokenKind> {
+    lex(src)
+        .unwrap()
+        .into_iter()
+        .map(|token| token.kind)
+        .filter(|kind| {
+            matches!(
+                kind,
+                TokenKind::Newline | TokenKind::Indent | TokenKind::Dedent
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn lex_indentation_as_indent_and_dedent() {
+    use TokenKind::{Dedent, Indent, Newline};
+
+    // <A>, then <B> and <C> under it, <D> under <C>, and <E> back at the top
+    let src = "<A>\n  <B>\n  <C>\n    <D>\n<E>";
+    let should_be = vec![
+        Newline, Indent, Newline, Newline, Indent, Newline, Dedent, Dedent, Newline,
+    ];
+
+    pretty_assertions::assert_eq!(layout(src), should_be);
+}
+
+#[test]
+fn lex_skips_blank_lines() {
+    use TokenKind::{Dedent, Indent, Newline};
+
+    let src = "\n<A>\n\n  <B>\n   \n  <C>\n\n";
+    let should_be = vec![Newline, Indent, Newline, Newline, Dedent];
+
+    pretty_assertions::assert_eq!(layout(src), should_be);
+}
+
+#[test]
+fn lex_inconsistent_dedent_is_an_error() {
+    let got = lex("<A>\n    <B>\n  <C>").unwrap_err();
+
+    assert_eq!(got.span, Span::new(12, 14));
+}
+
+#[test]
+fn lex_comments_as_trivia_of_the_next_token() {
+    let src = "// page\n<Column> /* end */\n  <!-- title -->\n  <Text> 8 // eight\n";
+    let tokens = lex(src).unwrap();
+    let trivia: Vec<(TokenKind, Vec<&str>)> = tokens
+        .iter()
+        .filter(|token| !token.trivia.is_empty())
+        .map(|token| {
+            let texts = token.trivia.iter().map(|comment| comment.text.as_str());
+            (token.kind.clone(), texts.collect())
+        })
+        .collect();
+
+    let should_be = vec![
+        (TokenKind::LessThan, vec![" page"]),
+        (TokenKind::LessThan, vec![" end ", " title "]),
+        (TokenKind::Dedent, vec![" eight"]),
+    ];
+    pretty_assertions::assert_eq!(trivia, should_be);
+}
+
+#[test]
+fn lex_comment_lines_are_blank_lines() {
+    use TokenKind::{Dedent, Indent, Newline};
+
+    let src = "<A>\n// about B\n  <B>\n    /* not a child */\n  <C>";
+    let should_be = vec![Newline, Indent, Newline, Newline, Dedent];
+
+    pretty_assertions::assert_eq!(layout(src), should_be);
+}
+
+#[test]
+fn lex_error_points_at_the_offending_character() {
+    let got = lex("<A>\n  <B> ~").unwrap_err();
+
+    assert_eq!(got.span, Span::new(10, 11));
+    assert_eq!(got.message, "unknown character '~'");
+}
+
+#[test]
+fn lex_style_block_values_as_single_tokens() {
+    let kinds: Vec<TokenKind> = lex("<Scaffold[bg:yellow-100 p:10]>")
+        .unwrap()
+        .into_iter()
+        .map(|token| token.kind)
+        .collect();
+    let should_be = vec![
+        TokenKind::LessThan,
+        TokenKind::from("Scaffold"),
+        TokenKind::OpenSquare,
+        TokenKind::from("bg"),
+        TokenKind::Colon,
+        TokenKind::StyleValue(String::from("yellow-100")),
+        TokenKind::from("p"),
+        TokenKind::Colon,
+        TokenKind::StyleValue(String::from("10")),
+        TokenKind::CloseSquare,
+        TokenKind::GreaterThan,
+        TokenKind::Newline,
+    ];
+
+    pretty_assertions::assert_eq!(kinds, should_be);
+}
+
+#[test]
+fn lex_comparison_and_logical_operators() {
+    let kinds: Vec<TokenKind> = lex("!a == b || c != d && e = f <= g >= h < i > j")
+        .unwrap()
+        .into_iter()
+        .map(|token| token.kind)
+        .filter(|kind| !matches!(kind, TokenKind::Identifier(_) | TokenKind::Newline))
+        .collect();
+    let should_be = vec![
+        TokenKind::Bang,
+        TokenKind::EqualsEquals,
+        TokenKind::OrOr,
+        TokenKind::NotEquals,
+        TokenKind::AndAnd,
+        TokenKind::Equals,
+        TokenKind::LessEquals,
+        TokenKind::GreaterEquals,
+        TokenKind::LessThan,
+        TokenKind::GreaterThan,
+    ];
+
+    pretty_assertions::assert_eq!(kinds, should_be);
+}
+
+#[test]
+fn lex_comparisons_before_brackets_as_expressions() {
+    let kinds: Vec<TokenKind> = lex("<if count < items[0]>")
+        .unwrap()
+        .into_iter()
+        .map(|token| token.kind)
+        .collect();
+
+    assert!(kinds.contains(&TokenKind::Number(0.0)), "{:?}", kinds);
+}
+
+#[test]
+fn lex_single_ampersand_is_an_error() {
+    assert!(lex("a & b").is_err());
+}
+
+#[test]
+fn lex_assignment_operators() {
+    let kinds: Vec<TokenKind> = lex("a += 1 - 2\nb -= c *= d /= e")
+        .unwrap()
+        .into_iter()
+        .map(|token| token.kind)
+        .filter(|kind| {
+            !matches!(
+                kind,
+                TokenKind::Identifier(_) | TokenKind::Number(_) | TokenKind::Newline
+            )
+        })
+        .collect();
+    let should_be = vec![
+        TokenKind::PlusEquals,
+        TokenKind::Minus,
+        TokenKind::MinusEquals,
+        TokenKind::AsteriskEquals,
+        TokenKind::SlashEquals,
+    ];
+
+    pretty_assertions::assert_eq!(kinds, should_be);
+}
+
+#[test]
+fn configured_indent_width_is_enforced() {
+    let options = LexOptions {
+        indent_width: Some(4),
+    };
+
+    assert!(lex_with("<A>\n    <B>\n        <C>\n<D>", &options).is_ok());
+    let err = lex_with("<A>\n  <B>", &options).unwrap_err();
+    assert_eq!(err.span, Span::new(4, 6));
+    assert!(lex("<A>\n  <B>").is_ok());
+}
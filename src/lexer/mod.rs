@@ -1,3 +1,4 @@
 mod combinators;
+#[allow(clippy::module_inception)]
 pub mod lexer;
 pub mod token_struct;
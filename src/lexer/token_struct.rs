@@ -2,34 +2,57 @@ use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[allow(missing_docs)]
-#[serde(tag = "type")]
+#[serde(tag = "type", content = "value")]
 pub enum TokenKind {
     // numbers
     Number(f64),
 
     // operators
-    Asterisk,    // *
-    Equals,      // =
-    Plus,        // +
-    Slash,       // /
-    LessThan,    // <
-    GreaterThan, // >
-    Minus,       // -
-    Colon,       // :
+    Asterisk,       // *
+    Equals,         // =
+    Plus,           // +
+    Slash,          // /
+    LessThan,       // <
+    GreaterThan,    // >
+    LessEquals,     // <=
+    GreaterEquals,  // >=
+    Minus,          // -
+    Colon,          // :
+    EqualsEquals,   // ==
+    NotEquals,      // !=
+    Bang,           // !
+    Question,       // ?
+    AndAnd,         // &&
+    OrOr,           // ||
+    PlusEquals,     // +=
+    MinusEquals,    // -=
+    AsteriskEquals, // *=
+    SlashEquals,    // /=
 
     // Words
     Identifier(String),
     QuotedString(String),
+    InterpolatedString(Vec<StringPart>), // "Counter: ${controller.counter}"
+    StyleValue(String),                  // yellow-100 inside [bg:yellow-100]
 
-    // WS
-    Indentation(usize),
+    // WS, like Python's tokenizer: `Newline` ends every non-blank line, then `Indent`
+    // opens a deeper level or one `Dedent` closes each level left
+    Newline,
+    Indent,
+    Dedent,
 
     // Keywords
     WidgetKW,
+    ImportKW,
+    IfKW,
+    ElseKW,
+    ForKW,
+    InKW,
 
     // Misc
     At,          // @
     Dot,         // .
+    Comma,       // ,
     CloseParen,  // )
     CloseSquare, // ]
     OpenParen,   // (
@@ -38,6 +61,14 @@ pub enum TokenKind {
     End,         // EOF
 }
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum StringPart {
+    Literal { value: String },
+    // `offset` is where `source` starts, in bytes from the opening quote
+    Interpolation { source: String, offset: usize },
+}
+
 impl From<String> for TokenKind {
     fn from(other: String) -> TokenKind {
         TokenKind::Identifier(other)
@@ -56,12 +87,129 @@ impl From<f64> for TokenKind {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+impl TokenKind {
+    /// The word a keyword is written as, `None` for other tokens.
+    pub fn keyword(&self) -> Option<&'static str> {
+        let word = match self {
+            TokenKind::WidgetKW => "widget",
+            TokenKind::ImportKW => "import",
+            TokenKind::IfKW => "if",
+            TokenKind::ElseKW => "else",
+            TokenKind::ForKW => "for",
+            TokenKind::InKW => "in",
+            _ => return None,
+        };
+
+        Some(word)
+    }
+
+    /// How the token is named in diagnostics: "expected `>`, found identifier `Text`"
+    pub fn describe(&self) -> String {
+        let symbol = match self {
+            TokenKind::Number(number) => return format!("number `{}`", number),
+            TokenKind::Identifier(id) => return format!("identifier `{}`", id),
+            TokenKind::QuotedString(_) | TokenKind::InterpolatedString(_) => {
+                return String::from("string")
+            }
+            TokenKind::StyleValue(value) => return format!("style value `{}`", value),
+            TokenKind::Newline => return String::from("new line"),
+            TokenKind::Indent => return String::from("indented line"),
+            TokenKind::Dedent => return String::from("end of indented block"),
+            TokenKind::End => return String::from("end of file"),
+            TokenKind::Asterisk => "*",
+            TokenKind::Equals => "=",
+            TokenKind::Plus => "+",
+            TokenKind::Slash => "/",
+            TokenKind::LessThan => "<",
+            TokenKind::GreaterThan => ">",
+            TokenKind::LessEquals => "<=",
+            TokenKind::GreaterEquals => ">=",
+            TokenKind::Minus => "-",
+            TokenKind::Colon => ":",
+            TokenKind::EqualsEquals => "==",
+            TokenKind::NotEquals => "!=",
+            TokenKind::Bang => "!",
+            TokenKind::Question => "?",
+            TokenKind::AndAnd => "&&",
+            TokenKind::OrOr => "||",
+            TokenKind::PlusEquals => "+=",
+            TokenKind::MinusEquals => "-=",
+            TokenKind::AsteriskEquals => "*=",
+            TokenKind::SlashEquals => "/=",
+            TokenKind::WidgetKW => "widget",
+            TokenKind::ImportKW => "import",
+            TokenKind::IfKW => "if",
+            TokenKind::ElseKW => "else",
+            TokenKind::ForKW => "for",
+            TokenKind::InKW => "in",
+            TokenKind::At => "@",
+            TokenKind::Dot => ".",
+            TokenKind::Comma => ",",
+            TokenKind::CloseParen => ")",
+            TokenKind::CloseSquare => "]",
+            TokenKind::OpenParen => "(",
+            TokenKind::OpenSquare => "[",
+            TokenKind::Semicolon => ";",
+        };
+
+        format!("`{}`", symbol)
+    }
+}
+
+/// Byte offsets into the source, `start` inclusive and `end` exclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    /// The span covering both `self` and `other`
+    pub fn to(self, other: Span) -> Span {
+        Span::new(self.start.min(other.start), self.end.max(other.end))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum CommentKind {
+    Line,   // // ...
+    Block,  // /* ... */
+    Markup, // <!-- ... -->
+}
+
+// `text` is what's between the delimiters, spaces included
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Comment {
+    pub kind: CommentKind,
+    pub text: String,
+    pub span: Span,
+}
+
+impl Comment {
+    /// The comment as written, delimiters included.
+    pub fn source(&self) -> String {
+        match self.kind {
+            CommentKind::Line => format!("//{}", self.text),
+            CommentKind::Block => format!("/*{}*/", self.text),
+            CommentKind::Markup => format!("<!--{}-->", self.text),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Token {
     pub kind: TokenKind,
+    // byte offsets into the source
     pub start: usize,
     pub end: usize,
     pub line: usize,
+    // the comments since the previous token that isn't a line break or indentation
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub trivia: Vec<Comment>,
 }
 
 impl Token {
@@ -71,6 +219,11 @@ impl Token {
             start,
             end,
             line,
+            trivia: Vec::new(),
         }
     }
+
+    pub fn span(&self) -> Span {
+        Span::new(self.start, self.end)
+    }
 }
@@ -0,0 +1,673 @@
+use super::protocol::{offset, path_to_uri, range, uri_to_path};
+use crate::{
+    cli::commands::{compile, parse_file},
+    config::config::Config,
+    diagnostics::diagnostic::{Diagnostic, Severity},
+    emitter::{
+        emitter::{SELF_WIDGET, SLOT_ATTRIBUTE},
+        listenable::LISTEN_ATTRIBUTE,
+    },
+    guard_clause,
+    lexer::{
+        lexer::lex,
+        token_struct::{Span, Token, TokenKind},
+    },
+    parser::ast_struct::{Import, Item, Node, WidgetDecl},
+    resolver::resolver::{locate_import, resolve_import, Module},
+};
+use serde_json::{json, Value};
+use std::{collections::HashMap, fs, path::Path};
+
+// LSP's SymbolKind values for what the outline shows
+const SYMBOL_MODULE: u32 = 2;
+const SYMBOL_CLASS: u32 = 5;
+const SYMBOL_FIELD: u32 = 8;
+const SYMBOL_STRING: u32 = 15;
+const SYMBOL_OBJECT: u32 = 19;
+const SYMBOL_OPERATOR: u32 = 25;
+
+// LSP's CompletionItemKind values
+const COMPLETION_PROPERTY: u32 = 10;
+const COMPLETION_CLASS: u32 = 7;
+
+/// The markup files open in the editor, by URI. Their text is used instead of
+/// what is on disk, which may be older. Widgets are looked up in the catalog of
+/// the project each file belongs to, like `build` does.
+#[derive(Debug, Default)]
+pub struct Documents {
+    texts: HashMap<String, String>,
+}
+
+// what the name under the cursor stands for
+#[derive(Debug, PartialEq)]
+enum Target {
+    Widget(String),
+    Prop { widget: String, name: String },
+    Attribute { widget: String, name: String },
+    Event { widget: String, name: String },
+    Import(String),
+}
+
+// a widget declaration and the document it was found in
+struct Declaration {
+    uri: String,
+    text: String,
+    decl: WidgetDecl,
+}
+
+impl Documents {
+    pub fn open(&mut self, uri: &str, text: &str) {
+        self.texts.insert(uri.to_string(), text.to_string());
+    }
+
+    pub fn close(&mut self, uri: &str) {
+        self.texts.remove(uri);
+    }
+
+    /// The `textDocument/publishDiagnostics` parameters for a document, what
+    /// `wdart check` would report for it.
+    pub fn diagnostics(&self, uri: &str) -> Value {
+        let text = self.texts.get(uri).map_or("", String::as_str);
+        let file = file_name(uri);
+        let diagnostics = compile(&file, text, &project_config(uri))
+            .err()
+            .unwrap_or_default();
+
+        let diagnostics: Vec<Value> = diagnostics
+            .iter()
+            .map(|diagnostic| lsp_diagnostic(uri, text, diagnostic))
+            .collect();
+        json!({ "uri": uri, "diagnostics": diagnostics })
+    }
+
+    pub fn hover(&self, uri: &str, position: &Value) -> Option<Value> {
+        let text = self.texts.get(uri)?;
+        let (target, span) = target_at(text, offset(text, position)?)?;
+        let config = project_config(uri);
+
+        let markdown = match target {
+            Target::Widget(name) if name == SELF_WIDGET => {
+                String::from("The widget being declared, its children are what `build()` returns")
+            }
+            Target::Widget(name) => match self.declaration(uri, &name) {
+                Some(found) => {
+                    let mut markdown = format!("```wdart\n{}\n```", signature(&found.decl));
+                    if found.uri != uri {
+                        markdown += &format!("\n\nDeclared in `{}`", file_name(&found.uri));
+                    }
+                    markdown
+                }
+                None => match config.catalog.get(&name) {
+                    Some(spec) => format!("```dart\n{}\n```", spec.signature(&name)),
+                    None => format!("Flutter widget `{}`", name),
+                },
+            },
+            Target::Prop { widget, name } => {
+                let param = self
+                    .declaration(uri, &widget)
+                    .and_then(|found| {
+                        let param = found.decl.params.iter().find(|param| param.name == name);
+                        param.map(|param| param.ty.clone())
+                    })
+                    .or_else(|| {
+                        let param = config.catalog.get(&widget)?.named(&name)?;
+                        Some(param.ty.clone())
+                    });
+                match param {
+                    Some(ty) => format!(
+                        "```wdart\n{}: {}\n```\n\nParameter of `{}`",
+                        name, ty, widget
+                    ),
+                    None => format!("`{}:` argument of `{}`", name, widget),
+                }
+            }
+            Target::Attribute { name, .. } if name == LISTEN_ATTRIBUTE => String::from(
+                "`listen=\"false\"` keeps the widget and its children from being wrapped in `ListenableBuilder`",
+            ),
+            Target::Attribute { name, .. } if name == SLOT_ATTRIBUTE => {
+                String::from("Passes the widget to its parent as the named argument given here")
+            }
+            Target::Attribute { widget, name } => {
+                format!("`{}:` argument of `{}`, as a string", name, widget)
+            }
+            Target::Event { widget, name } => match config.emit_options().events.resolve(&widget, &name) {
+                Some(param) => format!("`@{}` binds `{}:` of `{}`", name, param, widget),
+                None => format!("`@{}` is not a known event of `{}`", name, widget),
+            },
+            Target::Import(path) => format!("Imports `{}`", path),
+        };
+
+        Some(json!({
+            "contents": { "kind": "markdown", "value": markdown },
+            "range": range(text, span),
+        }))
+    }
+
+    /// Where a widget name is declared, or the file an import points at.
+    pub fn definition(&self, uri: &str, position: &Value) -> Option<Value> {
+        let text = self.texts.get(uri)?;
+        let (target, _) = target_at(text, offset(text, position)?)?;
+
+        match target {
+            Target::Widget(name) => {
+                let found = self.declaration(uri, &name)?;
+                Some(json!({ "uri": found.uri, "range": range(&found.text, found.decl.span) }))
+            }
+            Target::Import(path) => {
+                let module = Module {
+                    path: uri_to_path(uri)?,
+                    source: text.clone(),
+                    items: Vec::new(),
+                };
+                let import = Import {
+                    path,
+                    comments: Vec::new(),
+                    span: Span::default(),
+                };
+                let target = locate_import(&module, &import).ok()?;
+                let start = json!({ "line": 0, "character": 0 });
+                Some(json!({
+                    "uri": path_to_uri(&target),
+                    "range": { "start": start, "end": start },
+                }))
+            }
+            _ => None,
+        }
+    }
+
+    /// Widget names after `<`, and the arguments of the widget whose tag the
+    /// cursor is in.
+    pub fn completion(&self, uri: &str, position: &Value) -> Option<Value> {
+        let text = self.texts.get(uri)?;
+        let offset = offset(text, position)?;
+        let tokens = lex(&text[..offset]).ok()?;
+        let mut tokens: Vec<&Token> = tokens.iter().filter(|token| !is_layout(token)).collect();
+
+        // the word being typed is replaced by the completion
+        if let Some(token) = tokens.last() {
+            if matches!(token.kind, TokenKind::Identifier(_)) && token.end == offset {
+                tokens.pop();
+            }
+        }
+
+        let items = match tokens.last().map(|token| &token.kind) {
+            Some(TokenKind::LessThan) => self.widget_completions(uri),
+            Some(TokenKind::Colon | TokenKind::Equals | TokenKind::At | TokenKind::OpenSquare) => {
+                return None
+            }
+            _ => {
+                let tokens: Vec<Token> = tokens.into_iter().cloned().collect();
+                let widget = enclosing_widget(&tokens)?;
+                self.argument_completions(uri, &widget)
+            }
+        };
+        Some(Value::Array(items))
+    }
+
+    fn widget_completions(&self, uri: &str) -> Vec<Value> {
+        let text = self.texts.get(uri).map_or("", String::as_str);
+        let config = project_config(uri);
+        let (items, _) = parse_file(&file_name(uri), text, &config);
+
+        let declared = items.iter().filter_map(|item| match item {
+            Item::WidgetDecl(decl) => Some(json!({
+                "label": decl.name,
+                "kind": COMPLETION_CLASS,
+                "detail": signature(decl),
+            })),
+            _ => None,
+        });
+        let known = config.catalog.widgets.iter().map(|(name, spec)| {
+            json!({
+                "label": name,
+                "kind": COMPLETION_CLASS,
+                "detail": spec.signature(name),
+            })
+        });
+        declared.chain(known).collect()
+    }
+
+    // `name:` for each named argument of the widget
+    fn argument_completions(&self, uri: &str, widget: &str) -> Vec<Value> {
+        let params: Vec<(String, String)> = match self.declaration(uri, widget) {
+            Some(found) => found
+                .decl
+                .params
+                .iter()
+                .map(|param| (param.name.clone(), param.ty.clone()))
+                .collect(),
+            None => project_config(uri)
+                .catalog
+                .get(widget)
+                .map_or_else(Vec::new, |spec| {
+                    spec.params
+                        .iter()
+                        .chain(&spec.slots)
+                        .map(|param| (param.name.clone(), param.ty.clone()))
+                        .collect()
+                }),
+        };
+
+        params
+            .into_iter()
+            .map(|(name, ty)| {
+                json!({
+                    "label": name,
+                    "kind": COMPLETION_PROPERTY,
+                    "detail": ty,
+                    "insertText": format!("{}:", name),
+                })
+            })
+            .collect()
+    }
+
+    /// The outline of a document: its imports, declarations and widget tree.
+    pub fn symbols(&self, uri: &str) -> Option<Value> {
+        let text = self.texts.get(uri)?;
+        let (items, _) = parse_file(&file_name(uri), text, &project_config(uri));
+
+        let symbols: Vec<Value> = items
+            .iter()
+            .filter_map(|item| match item {
+                Item::Import(import) => Some(symbol(
+                    text,
+                    &import.path,
+                    SYMBOL_MODULE,
+                    import.span,
+                    import.span,
+                    Vec::new(),
+                )),
+                Item::WidgetDecl(decl) => {
+                    let mut children: Vec<Value> = decl
+                        .states
+                        .iter()
+                        .map(|state| {
+                            symbol(
+                                text,
+                                &state.name,
+                                SYMBOL_FIELD,
+                                state.span,
+                                state.span,
+                                Vec::new(),
+                            )
+                        })
+                        .collect();
+                    children.extend(node_symbols(text, &decl.body));
+                    let extent = decl
+                        .states
+                        .iter()
+                        .map(|state| state.span)
+                        .chain(decl.body.iter().map(extent))
+                        .fold(decl.span, Span::to);
+                    Some(symbol(
+                        text,
+                        &decl.name,
+                        SYMBOL_CLASS,
+                        extent,
+                        decl.span,
+                        children,
+                    ))
+                }
+                Item::Widget(widget) => node_symbols(text, &[Node::Widget(widget.clone())]).pop(),
+                Item::Error(_) | Item::Comments(_) => None,
+            })
+            .collect();
+        Some(Value::Array(symbols))
+    }
+
+    // the open document's text, or the file's
+    fn read(&self, path: &Path) -> Option<(String, String)> {
+        let uri = path_to_uri(path);
+        match self.texts.get(&uri) {
+            Some(text) => Some((uri, text.clone())),
+            None => fs::read_to_string(path).ok().map(|text| (uri, text)),
+        }
+    }
+
+    // Looks for `widget name` in the document, then in the markup files it imports.
+    fn declaration(&self, uri: &str, name: &str) -> Option<Declaration> {
+        let text = self.texts.get(uri)?;
+        let (items, _) = parse_file(&file_name(uri), text, &project_config(uri));
+
+        let mut sources = vec![(uri.to_string(), text.clone(), items.clone())];
+        // an unsaved document has nothing to import from
+        if let Some(path) = uri_to_path(uri) {
+            let module = Module {
+                path,
+                source: text.clone(),
+                items,
+            };
+            for import in module.imports() {
+                let imported = match resolve_import(&module, import) {
+                    Ok(Some(path)) => self.read(&path),
+                    _ => None,
+                };
+                if let Some((uri, text)) = imported {
+                    let (items, _) = parse_file(&file_name(&uri), &text, &project_config(&uri));
+                    sources.push((uri, text, items));
+                }
+            }
+        }
+
+        sources.into_iter().find_map(|(uri, text, items)| {
+            let decl = items.into_iter().find_map(|item| match item {
+                Item::WidgetDecl(decl) if decl.name == name => Some(decl),
+                _ => None,
+            })?;
+            Some(Declaration { uri, text, decl })
+        })
+    }
+}
+
+// a broken `wdart.toml` is reported by `check`, the editor keeps working with the defaults
+fn project_config(uri: &str) -> Config {
+    uri_to_path(uri)
+        .and_then(|path| Config::discover(&path).ok())
+        .unwrap_or_default()
+}
+
+fn file_name(uri: &str) -> String {
+    uri_to_path(uri).map_or_else(|| uri.to_string(), |path| path.display().to_string())
+}
+
+fn lsp_diagnostic(uri: &str, text: &str, diagnostic: &Diagnostic) -> Value {
+    let severity = match diagnostic.severity {
+        Severity::Error => 1,
+        Severity::Warning => 2,
+        Severity::Note => 3,
+    };
+    let mut message = diagnostic.message.clone();
+    for note in &diagnostic.notes {
+        message += &format!("\nnote: {}", note);
+    }
+    let related: Vec<Value> = diagnostic
+        .labels
+        .iter()
+        .filter(|label| label.span != diagnostic.span && !label.message.is_empty())
+        .map(|label| {
+            json!({
+                "location": { "uri": uri, "range": range(text, label.span) },
+                "message": label.message,
+            })
+        })
+        .collect();
+
+    json!({
+        "range": range(text, diagnostic.span),
+        "severity": severity,
+        "source": "wdart",
+        "message": message,
+        "relatedInformation": related,
+    })
+}
+
+// widget CounterPage(controller: CounterPageController)
+fn signature(decl: &WidgetDecl) -> String {
+    let params: Vec<String> = decl
+        .params
+        .iter()
+        .map(|param| format!("{}: {}", param.name, param.ty))
+        .collect();
+    format!("widget {}({})", decl.name, params.join(", "))
+}
+
+// Finds what the token at `offset` names from the tokens around it, which still
+// works on lines the parser gives up on while typing.
+fn target_at(text: &str, offset: usize) -> Option<(Target, Span)> {
+    let tokens = lex(text).ok()?;
+    let index = tokens
+        .iter()
+        .position(|token| token.start <= offset && offset <= token.end && is_named(token))?;
+    let token = &tokens[index];
+    let kind = |index: usize| tokens.get(index).map(|token| &token.kind);
+
+    if let TokenKind::QuotedString(path) = &token.kind {
+        guard_clause!(
+            index == 0 || kind(index - 1) != Some(&TokenKind::ImportKW),
+            None
+        );
+        return Some((Target::Import(path.clone()), token.span()));
+    }
+
+    let name = match &token.kind {
+        TokenKind::Identifier(name) => name.clone(),
+        _ => return None,
+    };
+    let previous = index.checked_sub(1).and_then(kind);
+    if previous == Some(&TokenKind::LessThan) {
+        return Some((Target::Widget(name), token.span()));
+    }
+
+    let widget = enclosing_widget(&tokens[..index])?;
+    let target = match (previous, kind(index + 1)) {
+        (Some(TokenKind::At), _) => Target::Event { widget, name },
+        (_, Some(TokenKind::Colon)) => Target::Prop { widget, name },
+        (_, Some(TokenKind::Equals)) => Target::Attribute { widget, name },
+        _ => return None,
+    };
+    Some((target, token.span()))
+}
+
+fn is_named(token: &Token) -> bool {
+    matches!(
+        token.kind,
+        TokenKind::Identifier(_) | TokenKind::QuotedString(_)
+    )
+}
+
+fn is_layout(token: &Token) -> bool {
+    matches!(
+        token.kind,
+        TokenKind::Newline | TokenKind::Indent | TokenKind::Dedent
+    )
+}
+
+// The widget whose tag the tokens end in, if they do. Style blocks are skipped,
+// their keys are not arguments.
+fn enclosing_widget(before: &[Token]) -> Option<String> {
+    let mut closed_square = false;
+    for (index, token) in before.iter().enumerate().rev() {
+        match &token.kind {
+            TokenKind::Newline | TokenKind::GreaterThan => return None,
+            TokenKind::CloseSquare => closed_square = true,
+            TokenKind::OpenSquare if !closed_square => return None,
+            TokenKind::OpenSquare => closed_square = false,
+            TokenKind::LessThan => {
+                return match before.get(index + 1).map(|token| &token.kind) {
+                    Some(TokenKind::Identifier(name)) => Some(name.clone()),
+                    _ => None,
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn symbol(
+    text: &str,
+    name: &str,
+    kind: u32,
+    extent: Span,
+    selection: Span,
+    children: Vec<Value>,
+) -> Value {
+    json!({
+        "name": name,
+        "kind": kind,
+        "range": range(text, extent),
+        "selectionRange": range(text, selection),
+        "children": children,
+    })
+}
+
+fn node_symbols(text: &str, nodes: &[Node]) -> Vec<Value> {
+    nodes
+        .iter()
+        .flat_map(|node| match node {
+            Node::Widget(widget) => vec![symbol(
+                text,
+                &widget.name,
+                SYMBOL_OBJECT,
+                extent(node),
+                widget.span,
+                node_symbols(text, &widget.children),
+            )],
+            Node::Text(content) => vec![symbol(
+                text,
+                &text[content.span.start..content.span.end],
+                SYMBOL_STRING,
+                content.span,
+                content.span,
+                Vec::new(),
+            )],
+            // one symbol per branch, named after its tag: `else if controller.failed`
+            Node::If(node) => node
+                .branches
+                .iter()
+                .map(|branch| {
+                    let extent = branch
+                        .children
+                        .iter()
+                        .map(extent)
+                        .fold(branch.span, Span::to);
+                    symbol(
+                        text,
+                        &text[branch.span.start + 1..branch.span.end - 1],
+                        SYMBOL_OPERATOR,
+                        extent,
+                        branch.span,
+                        node_symbols(text, &branch.children),
+                    )
+                })
+                .collect(),
+            Node::For(node) => vec![symbol(
+                text,
+                &text[node.span.start + 1..node.span.end - 1],
+                SYMBOL_OPERATOR,
+                node.children.iter().map(extent).fold(node.span, Span::to),
+                node.span,
+                node_symbols(text, &node.children),
+            )],
+            Node::Error(_) => Vec::new(),
+        })
+        .collect()
+}
+
+// a node's span only covers its own line, the outline wants its children too
+fn extent(node: &Node) -> Span {
+    match node {
+        Node::Widget(widget) => widget
+            .children
+            .iter()
+            .map(extent)
+            .fold(widget.span, Span::to),
+        Node::If(node) => node
+            .branches
+            .iter()
+            .flat_map(|branch| branch.children.iter().map(extent))
+            .fold(
+                node.branches
+                    .iter()
+                    .map(|branch| branch.span)
+                    .fold(node.span, Span::to),
+                Span::to,
+            ),
+        Node::For(node) => node.children.iter().map(extent).fold(node.span, Span::to),
+        _ => node.span(),
+    }
+}
+
+#[cfg(test)]
+fn target(src: &str, offset: usize) -> Option<Target> {
+    target_at(src, offset).map(|(target, _)| target)
+}
+
+#[test]
+fn find_what_the_cursor_is_on() {
+    let src = "import \"x.flutter\";\n<Column[p:10] slot=\"a\" gap:4 @tap:go>";
+
+    assert_eq!(
+        target(src, 9),
+        Some(Target::Import(String::from("x.flutter")))
+    );
+    assert_eq!(
+        target(src, 22),
+        Some(Target::Widget(String::from("Column")))
+    );
+    // the style key
+    assert_eq!(target(src, 28), None);
+    let widget = String::from("Column");
+    assert_eq!(
+        target(src, 34),
+        Some(Target::Attribute {
+            widget: widget.clone(),
+            name: String::from("slot")
+        })
+    );
+    assert_eq!(
+        target(src, 43),
+        Some(Target::Prop {
+            widget: widget.clone(),
+            name: String::from("gap")
+        })
+    );
+    assert_eq!(
+        target(src, 50),
+        Some(Target::Event {
+            widget,
+            name: String::from("tap")
+        })
+    );
+}
+
+#[cfg(test)]
+fn labels(completions: Option<Value>) -> Vec<String> {
+    let completions = completions.unwrap_or_default();
+    let items = completions.as_array().cloned().unwrap_or_default();
+    items
+        .iter()
+        .map(|item| item["label"].as_str().unwrap_or_default().to_string())
+        .collect()
+}
+
+#[test]
+fn complete_widgets_and_their_arguments() {
+    let mut documents = Documents::default();
+    let uri = "file:///app.flutter";
+    documents.open(
+        uri,
+        "widget Tile(title: String)\n  <Text> title\n<Column m>\n  <Pad\n  <Tile ti>\n  <Icon size:",
+    );
+    let at = |line: u32, character: u32| json!({ "line": line, "character": character });
+
+    let widgets = labels(documents.completion(uri, &at(3, 6)));
+    assert_eq!(widgets[0], "Tile");
+    assert!(widgets.contains(&String::from("Padding")));
+    assert!(
+        labels(documents.completion(uri, &at(2, 9))).contains(&String::from("mainAxisAlignment"))
+    );
+    assert_eq!(labels(documents.completion(uri, &at(4, 10))), vec!["title"]);
+    assert_eq!(documents.completion(uri, &at(5, 13)), None);
+}
+
+#[test]
+fn project_imports_and_catalogs_are_the_compiler_ones() {
+    let project = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/package");
+    let home = project.join("lib/home.flutter").canonicalize().unwrap();
+    let card = project
+        .join("lib/cards/card.flutter")
+        .canonicalize()
+        .unwrap();
+    let uri = path_to_uri(&home);
+    let mut documents = Documents::default();
+    documents.open(&uri, &fs::read_to_string(&home).unwrap());
+    let at = |line: u32, character: u32| json!({ "line": line, "character": character });
+
+    // `package:my_app/cards/card.flutter`
+    let import = documents.definition(&uri, &at(0, 20)).unwrap();
+    assert_eq!(import["uri"], path_to_uri(&card));
+    let widget = documents.definition(&uri, &at(4, 6)).unwrap();
+    assert_eq!(widget["uri"], path_to_uri(&card));
+    assert!(labels(documents.completion(&uri, &at(4, 5))).contains(&String::from("Avatar")));
+}
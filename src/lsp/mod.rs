@@ -0,0 +1,3 @@
+pub mod documents;
+pub mod protocol;
+pub mod server;
@@ -0,0 +1,162 @@
+use crate::lexer::token_struct::Span;
+use serde_json::{json, Value};
+use std::{
+    io::{self, BufRead, Write},
+    path::{Path, PathBuf},
+};
+
+// JSON-RPC error codes the server answers with
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+
+/// Reads the next `Content-Length` framed message, or `None` at the end of the input.
+pub fn read_message(input: &mut impl BufRead) -> io::Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            let value = value.trim().parse().map_err(|_| invalid_data(header))?;
+            length = Some(value);
+        }
+    }
+
+    let length = length.ok_or_else(|| invalid_data("missing Content-Length header"))?;
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(|err| invalid_data(&err.to_string()))
+}
+
+pub fn write_message(output: &mut impl Write, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+pub fn response(id: &Value, result: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "result": result })
+}
+
+pub fn error_response(id: &Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+pub fn notification(method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "method": method, "params": params })
+}
+
+/// The LSP position of a byte offset: a zero-based line, and a column counted in
+/// UTF-16 code units as editors do.
+pub fn position(text: &str, offset: usize) -> Value {
+    let before = &text[..offset.min(text.len())];
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+    let line = before.matches('\n').count();
+    let character: usize = before[line_start..].chars().map(char::len_utf16).sum();
+
+    json!({ "line": line, "character": character })
+}
+
+pub fn range(text: &str, span: Span) -> Value {
+    json!({ "start": position(text, span.start), "end": position(text, span.end) })
+}
+
+/// The byte offset of an LSP position, clamped to the end of its line.
+pub fn offset(text: &str, position: &Value) -> Option<usize> {
+    let line = position.get("line")?.as_u64()? as usize;
+    let character = position.get("character")?.as_u64()? as usize;
+
+    let line_start = if line == 0 {
+        0
+    } else {
+        text.match_indices('\n').nth(line - 1)?.0 + 1
+    };
+    let mut units = 0;
+    for (index, ch) in text[line_start..].char_indices() {
+        if units >= character || ch == '\n' {
+            return Some(line_start + index);
+        }
+        units += ch.len_utf16();
+    }
+
+    Some(text.len())
+}
+
+// file:///home/me/app%20one.flutter => /home/me/app one.flutter
+pub fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let path = uri.strip_prefix("file://")?;
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::new();
+    let mut index = 0;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'%' => {
+                let hex = path.get(index + 1..index + 3)?;
+                decoded.push(u8::from_str_radix(hex, 16).ok()?);
+                index += 3;
+            }
+            byte => {
+                decoded.push(byte);
+                index += 1;
+            }
+        }
+    }
+
+    String::from_utf8(decoded).ok().map(PathBuf::from)
+}
+
+pub fn path_to_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for byte in path.to_string_lossy().bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' => {
+                uri.push(byte as char)
+            }
+            _ => uri += &format!("%{:02X}", byte),
+        }
+    }
+    uri
+}
+
+#[test]
+fn read_framed_messages() {
+    let input = "Content-Length: 14\r\n\r\n{\"id\":1,\"a\":2}Content-Length: 2\r\nContent-Type: x\r\n\r\n{}";
+    let mut input = io::Cursor::new(input);
+
+    assert_eq!(
+        read_message(&mut input).unwrap(),
+        Some(json!({"id": 1, "a": 2}))
+    );
+    assert_eq!(read_message(&mut input).unwrap(), Some(json!({})));
+    assert_eq!(read_message(&mut input).unwrap(), None);
+}
+
+#[test]
+fn positions_count_utf16_units() {
+    let text = "<A>\n  <Bé> \"😀x\"";
+
+    // the emoji is 4 bytes but 2 UTF-16 units
+    assert_eq!(position(text, 17), json!({"line": 1, "character": 10}));
+    assert_eq!(offset(text, &json!({"line": 1, "character": 10})), Some(17));
+    assert_eq!(offset(text, &json!({"line": 0, "character": 99})), Some(3));
+}
+
+#[test]
+fn convert_between_uris_and_paths() {
+    let path = Path::new("/home/me/my app/page.flutter");
+
+    assert_eq!(path_to_uri(path), "file:///home/me/my%20app/page.flutter");
+    assert_eq!(uri_to_path(&path_to_uri(path)).as_deref(), Some(path));
+}
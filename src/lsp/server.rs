@@ -0,0 +1,269 @@
+use super::{
+    documents::Documents,
+    protocol::{
+        error_response, notification, read_message, response, write_message, INVALID_PARAMS,
+        METHOD_NOT_FOUND,
+    },
+};
+use crate::cli::commands::{EXIT_FAILED, EXIT_OK};
+use serde_json::{json, Value};
+use std::io::{BufRead, Write};
+
+// the whole text is sent on every change
+const TEXT_DOCUMENT_SYNC_FULL: u32 = 1;
+
+#[derive(Debug, Default)]
+pub struct Server {
+    documents: Documents,
+    shutdown: bool,
+    exit: bool,
+}
+
+impl Server {
+    /// Answers one message from the client with the responses and notifications
+    /// to send back.
+    pub fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let method = match message.get("method").and_then(Value::as_str) {
+            Some(method) => method,
+            // a response to a request of ours, the server sends none
+            None => return Vec::new(),
+        };
+        let id = message.get("id");
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+
+        match (method, id) {
+            ("initialize", Some(id)) => vec![response(id, capabilities())],
+            ("shutdown", Some(id)) => {
+                self.shutdown = true;
+                vec![response(id, Value::Null)]
+            }
+            ("exit", _) => {
+                self.exit = true;
+                Vec::new()
+            }
+            ("textDocument/didOpen" | "textDocument/didChange" | "textDocument/didClose", None) => {
+                self.sync(method, &params).unwrap_or_default()
+            }
+            (
+                "textDocument/hover"
+                | "textDocument/definition"
+                | "textDocument/documentSymbol"
+                | "textDocument/completion",
+                Some(id),
+            ) => match self.query(method, &params) {
+                Some(result) => vec![response(id, result)],
+                None => vec![error_response(id, INVALID_PARAMS, "unknown document")],
+            },
+            (_, Some(id)) => vec![error_response(
+                id,
+                METHOD_NOT_FOUND,
+                &format!("unsupported method `{}`", method),
+            )],
+            // `initialized`, `$/cancelRequest` and other notifications need no answer
+            (_, None) => Vec::new(),
+        }
+    }
+
+    pub fn should_exit(&self) -> bool {
+        self.exit
+    }
+
+    // Keeps the open documents up to date and reports their diagnostics.
+    fn sync(&mut self, method: &str, params: &Value) -> Option<Vec<Value>> {
+        let document = params.get("textDocument")?;
+        let uri = document.get("uri")?.as_str()?;
+
+        match method {
+            "textDocument/didOpen" => self.documents.open(uri, document.get("text")?.as_str()?),
+            "textDocument/didChange" => {
+                let changes = params.get("contentChanges")?.as_array()?;
+                let text = changes.last()?.get("text")?.as_str()?;
+                self.documents.open(uri, text);
+            }
+            _ => {
+                self.documents.close(uri);
+                let cleared = json!({ "uri": uri, "diagnostics": [] });
+                return Some(vec![notification(
+                    "textDocument/publishDiagnostics",
+                    cleared,
+                )]);
+            }
+        }
+
+        Some(vec![notification(
+            "textDocument/publishDiagnostics",
+            self.documents.diagnostics(uri),
+        )])
+    }
+
+    // `None` when the request doesn't name an open document, `Some(null)` when
+    // there is nothing at the position
+    fn query(&self, method: &str, params: &Value) -> Option<Value> {
+        let uri = params.get("textDocument")?.get("uri")?.as_str()?;
+        let position = params.get("position").unwrap_or(&Value::Null);
+
+        let result = match method {
+            "textDocument/hover" => self.documents.hover(uri, position),
+            "textDocument/definition" => self.documents.definition(uri, position),
+            "textDocument/completion" => self.documents.completion(uri, position),
+            _ => Some(self.documents.symbols(uri)?),
+        };
+        Some(result.unwrap_or(Value::Null))
+    }
+}
+
+fn capabilities() -> Value {
+    json!({
+        "capabilities": {
+            "textDocumentSync": TEXT_DOCUMENT_SYNC_FULL,
+            "hoverProvider": true,
+            "definitionProvider": true,
+            "documentSymbolProvider": true,
+            "completionProvider": { "triggerCharacters": ["<"] },
+        },
+        "serverInfo": { "name": "wdart", "version": env!("CARGO_PKG_VERSION") },
+    })
+}
+
+/// Runs the language server over `input` and `output`, stdin and stdout for
+/// `wdart lsp`, until the client says `exit`. Like other servers, it only exits
+/// successfully after a `shutdown` request.
+pub fn serve(mut input: impl BufRead, mut output: impl Write) -> u8 {
+    let mut server = Server::default();
+
+    loop {
+        let message = match read_message(&mut input) {
+            Ok(Some(message)) => message,
+            Ok(None) => return EXIT_FAILED,
+            Err(err) => {
+                eprintln!("error: cannot read message: {}", err);
+                return EXIT_FAILED;
+            }
+        };
+
+        for reply in server.handle(&message) {
+            if let Err(err) = write_message(&mut output, &reply) {
+                eprintln!("error: cannot write message: {}", err);
+                return EXIT_FAILED;
+            }
+        }
+
+        if server.should_exit() {
+            return if server.shutdown {
+                EXIT_OK
+            } else {
+                EXIT_FAILED
+            };
+        }
+    }
+}
+
+// Plays a whole session against `serve`, the way an editor would, and returns
+// its exit code and the messages the server sent.
+#[cfg(test)]
+fn session(messages: &[Value]) -> (u8, Vec<Value>) {
+    let mut input = Vec::new();
+    for message in messages {
+        write_message(&mut input, message).unwrap();
+    }
+    let mut output = Vec::new();
+    let code = serve(std::io::Cursor::new(input), &mut output);
+
+    let mut output = std::io::Cursor::new(output);
+    let mut replies = Vec::new();
+    while let Some(reply) = read_message(&mut output).unwrap() {
+        replies.push(reply);
+    }
+    (code, replies)
+}
+
+#[cfg(test)]
+fn request(id: u64, method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })
+}
+
+#[test]
+fn scripted_editor_session() {
+    use super::protocol::path_to_uri;
+
+    let fixtures = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/imports");
+    let app = fixtures.join("app.flutter").canonicalize().unwrap();
+    let counter = fixtures.join("counter.flutter").canonicalize().unwrap();
+    let uri = path_to_uri(&app);
+    let text = std::fs::read_to_string(&app).unwrap();
+    let at = |line: u32, character: u32| {
+        json!({
+            "textDocument": { "uri": uri },
+            "position": { "line": line, "character": character },
+        })
+    };
+
+    let (code, replies) = session(&[
+        request(1, "initialize", json!({ "capabilities": {} })),
+        notification("initialized", json!({})),
+        notification(
+            "textDocument/didOpen",
+            json!({ "textDocument": { "uri": uri, "languageId": "wdart", "version": 1, "text": text } }),
+        ),
+        // <CounterView>, declared in counter.flutter
+        request(2, "textDocument/hover", at(5, 6)),
+        request(3, "textDocument/definition", at(5, 6)),
+        request(4, "textDocument/definition", at(1, 10)),
+        request(
+            5,
+            "textDocument/documentSymbol",
+            json!({ "textDocument": { "uri": uri } }),
+        ),
+        notification(
+            "textDocument/didChange",
+            json!({
+                "textDocument": { "uri": uri, "version": 2 },
+                "contentChanges": [{ "text": "<Column\n" }],
+            }),
+        ),
+        request(6, "textDocument/formatting", json!({})),
+        request(7, "shutdown", Value::Null),
+        notification("exit", Value::Null),
+    ]);
+
+    assert_eq!(code, EXIT_OK);
+    assert_eq!(replies.len(), 9, "{:#?}", replies);
+    assert_eq!(replies[0]["result"]["capabilities"]["hoverProvider"], true);
+    assert_eq!(replies[1]["params"]["diagnostics"], json!([]));
+    assert_eq!(
+        replies[2]["result"]["contents"]["value"],
+        format!(
+            "```wdart\nwidget CounterView()\n```\n\nDeclared in `{}`",
+            counter.display()
+        )
+    );
+    assert_eq!(replies[3]["result"]["uri"], path_to_uri(&counter));
+    assert_eq!(
+        replies[3]["result"]["range"]["start"],
+        json!({ "line": 2, "character": 0 })
+    );
+    assert_eq!(replies[4]["result"]["uri"], path_to_uri(&counter));
+
+    let symbols = &replies[5]["result"];
+    assert_eq!(symbols[2]["name"], "App");
+    assert_eq!(symbols[2]["children"][0]["name"], "Self");
+    assert_eq!(
+        symbols[2]["children"][0]["children"][0]["name"],
+        "CounterView"
+    );
+
+    let diagnostics = &replies[6]["params"]["diagnostics"];
+    assert_eq!(diagnostics[0]["severity"], 1);
+    assert_eq!(
+        diagnostics[0]["range"]["start"],
+        json!({ "line": 0, "character": 7 })
+    );
+    assert_eq!(replies[7]["error"]["code"], METHOD_NOT_FOUND);
+}
+
+#[test]
+fn exit_without_shutdown_fails() {
+    let (code, _) = session(&[notification("exit", Value::Null)]);
+
+    assert_eq!(code, EXIT_FAILED);
+}
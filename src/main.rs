@@ -1,39 +1,34 @@
+pub mod catalog;
+pub mod cli;
+pub mod config;
+pub mod diagnostics;
+pub mod emitter;
+pub mod formatter;
 pub mod helpers;
 pub mod lexer;
+pub mod lsp;
 pub mod parser;
+pub mod resolver;
+pub mod schema;
 
-use std::collections::VecDeque;
+use cli::{
+    args::{parse_args, USAGE},
+    commands::{run, EXIT_OK, EXIT_USAGE},
+};
+use std::{env, process::ExitCode};
 
-use lexer::lexer::lex;
-use parser::parser::parse_program;
+fn main() -> ExitCode {
+    let code = match parse_args(env::args().skip(1)) {
+        Ok(Some(args)) => run(&args),
+        Ok(None) => {
+            print!("{}", USAGE);
+            EXIT_OK
+        }
+        Err(message) => {
+            eprint!("error: {}\n\n{}", message, USAGE);
+            EXIT_USAGE
+        }
+    };
 
-fn main() {
-    // widget CounterPage(controller: CounterPageController)
-    let input = "
-<Button1[bg:yellow-100] @tap:controller.increment>
-  <Text> \"Increment\"
-<Button2[bg:red-100] @tap:controller.decrement>
-  <Text> \"Decrement\"
-<Container>
-  <GlowingBox>
-    <WavingAnimation>
-      <Text> \"Happy hacking!\"
-  <FittedBox>
-    <Text> \"Counter: \" + controller.counter
-      ";
-
-    let input = "
-<Button>
-  <Text1>
-  <Text2>
-  <Text3>
-  <Text4>
-    ";
-
-    let tokens = lex(input).unwrap();
-    // println!("{:#?}", tokens);
-
-    let ast = parse_program(&mut VecDeque::from(tokens)).unwrap();
-    println!("================");
-    println!("{:#?}", ast);
+    ExitCode::from(code)
 }
@@ -0,0 +1,290 @@
+pub use crate::lexer::token_struct::{Comment, CommentKind, Span};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum Item {
+    Import(Import),
+    WidgetDecl(WidgetDecl),
+    Widget(Widget),
+    Error(ErrorNode),
+    Comments(TrailingComments),
+}
+
+// import "./counter_page.controller.dart";
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Import {
+    pub path: String,
+    pub comments: Vec<Comment>,
+    pub span: Span,
+}
+
+// widget CounterPage(controller: CounterPageController)
+//   <Self>
+//     ...
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WidgetDecl {
+    pub name: String,
+    pub params: Vec<Param>,
+    // declaring any makes the widget stateful
+    pub states: Vec<StateDecl>,
+    pub body: Vec<Node>,
+    pub comments: Vec<Comment>,
+    // the `widget Name(...)` header
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Param {
+    pub name: String,
+    pub ty: String,
+    pub span: Span,
+}
+
+// state count: int = 0
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StateDecl {
+    pub name: String,
+    pub ty: String,
+    pub init: Expr,
+    pub comments: Vec<Comment>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum Node {
+    Widget(Widget),
+    Text(TextNode),
+    If(IfNode),
+    For(ForNode),
+    Error(ErrorNode),
+}
+
+impl Node {
+    pub fn span(&self) -> Span {
+        match self {
+            Node::Widget(widget) => widget.span,
+            Node::Text(text) => text.span,
+            Node::If(node) => node.span,
+            Node::For(node) => node.span,
+            Node::Error(error) => error.span,
+        }
+    }
+}
+
+// stands in for the lines skipped after a parse error, so the rest of the tree
+// keeps its shape
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ErrorNode {
+    pub span: Span,
+}
+
+// the comments after the last item, which no item follows to carry them
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrailingComments {
+    pub comments: Vec<Comment>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Widget {
+    pub name: String,
+    pub style: Vec<StyleProp>,
+    pub attributes: Vec<Attribute>,
+    pub props: Vec<Prop>,
+    pub events: Vec<EventBinding>,
+    // <Text> "Increment"
+    pub content: Option<Expr>,
+    pub children: Vec<Node>,
+    // the comments on the lines before the tag
+    pub comments: Vec<Comment>,
+    // the `<Name ...>` tag
+    pub span: Span,
+}
+
+// a bare "Counter: ${controller.counter}" line between widgets
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TextNode {
+    pub content: Expr,
+    pub comments: Vec<Comment>,
+    pub span: Span,
+}
+
+// <if controller.loading>
+//   <CircularProgressIndicator>
+// <else if controller.failed>
+//   <Text> "Failed"
+// <else>
+//   ...
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IfNode {
+    // `<if>`, then each `<else if>`, then `<else>` if there is one
+    pub branches: Vec<Branch>,
+    pub comments: Vec<Comment>,
+    // the `<if ...>` tag
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Branch {
+    // `None` for `<else>`
+    pub condition: Option<Expr>,
+    pub children: Vec<Node>,
+    // the comments on the lines before an `<else>` tag
+    pub comments: Vec<Comment>,
+    pub span: Span,
+}
+
+// <for entry, index in controller.history key:entry.id>
+//   <Text> "${index}: ${entry.title}"
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ForNode {
+    pub item: String,
+    pub index: Option<String>,
+    pub iterable: Expr,
+    // gives the widget built for each item a `ValueKey`
+    pub key: Option<Expr>,
+    pub children: Vec<Node>,
+    pub comments: Vec<Comment>,
+    // the `<for ...>` tag
+    pub span: Span,
+}
+
+// <Column[p:10 align:center]>
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StyleProp {
+    pub key: String,
+    pub value: String,
+    pub span: Span,
+}
+
+// slot="appBar"
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Attribute {
+    pub name: String,
+    pub value: String,
+    pub span: Span,
+}
+
+// itemCount:controller.history.length
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Prop {
+    pub name: String,
+    pub value: Expr,
+    pub span: Span,
+}
+
+// @tap:controller.increment
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EventBinding {
+    pub name: String,
+    pub handler: Expr,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+impl Expr {
+    pub fn new(kind: ExprKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+
+    /// The bare names the expression reads, in order: `a.b + c[d]` reads `a`, `c`
+    /// and `d`. Members are not names.
+    pub fn identifiers(&self) -> Vec<&str> {
+        match &self.kind {
+            ExprKind::Identifier(name) => vec![name.as_str()],
+            ExprKind::String(_) | ExprKind::Number(_) | ExprKind::Bool(_) | ExprKind::Null => {
+                Vec::new()
+            }
+            ExprKind::Interpolated(segments) => segments
+                .iter()
+                .flat_map(|segment| match segment {
+                    StringSegment::Literal(_) => Vec::new(),
+                    StringSegment::Expr(expr) => expr.identifiers(),
+                })
+                .collect(),
+            ExprKind::Member(object, _) => object.identifiers(),
+            ExprKind::Unary(_, operand) | ExprKind::Paren(operand) => operand.identifiers(),
+            ExprKind::Index(lhs, rhs)
+            | ExprKind::Binary(_, lhs, rhs)
+            | ExprKind::Assign(_, lhs, rhs) => {
+                let mut names = lhs.identifiers();
+                names.extend(rhs.identifiers());
+                names
+            }
+            ExprKind::Call(callee, args) => {
+                let mut names = callee.identifiers();
+                names.extend(args.iter().flat_map(Expr::identifiers));
+                names
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value")]
+pub enum ExprKind {
+    String(String),
+    // "Counter: ${controller.counter}"
+    Interpolated(Vec<StringSegment>),
+    Number(f64),
+    Bool(bool),
+    Null,
+    Identifier(String),
+    // controller.increment
+    Member(Box<Expr>, String),
+    // controller.history[index]
+    Index(Box<Expr>, Box<Expr>),
+    // controller.format(count, 2)
+    Call(Box<Expr>, Vec<Expr>),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    // (a + b), kept so the generated Dart groups the same way
+    Paren(Box<Expr>),
+    // count += 1, only as an event handler
+    Assign(AssignOp, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum AssignOp {
+    Set,
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum UnaryOp {
+    Neg,
+    Not,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Eq,
+    NotEq,
+    Lt,
+    Gt,
+    LtEq,
+    GtEq,
+    And,
+    Or,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value")]
+pub enum StringSegment {
+    Literal(String),
+    Expr(Expr),
+}
@@ -0,0 +1,460 @@
+use super::{
+    ast_struct::{AssignOp, BinaryOp, Expr, ExprKind, Span, StringSegment, UnaryOp},
+    parser::{expect, expect_identifier, take_token, ParseError},
+};
+use crate::lexer::{
+    lexer::lex,
+    token_struct::{StringPart, Token as Lexeme, TokenKind},
+};
+use anyhow::Result;
+use std::collections::VecDeque;
+
+// binding powers, higher binds tighter; postfix `.`, `[]` and `()` bind tightest
+const LOGICAL_OR: u8 = 4;
+const LOGICAL_AND: u8 = 6;
+const EQUALITY: u8 = 8;
+const RELATIONAL: u8 = 9;
+const ADDITIVE: u8 = 10;
+const MULTIPLICATIVE: u8 = 20;
+const PREFIX: u8 = 30;
+
+// What a `>` is, in the tags it may close
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Closing {
+    // outside tags and inside brackets: a comparison
+    Never,
+    // widget tags, whose content may follow on the line: the end of the tag, so
+    // comparisons go in parentheses, `enabled:(count > 0)`
+    Always,
+    // `<if>` and `<for>` tags, which end their line: the end of the tag when
+    // nothing follows it
+    AtEndOfLine,
+}
+
+/// Pratt parser for the expressions used in content, attribute values, event
+/// handlers and conditions: `"Counter: " + controller.counter`,
+/// `controller.history[index]`, `!items.isEmpty && count == 0`, ...
+pub fn parse_expr(tokens: &mut VecDeque<Lexeme>) -> Result<Expr, ParseError> {
+    parse_expr_bp(tokens, 0, Closing::Never)
+}
+
+/// An expression in a widget tag, the value of a prop, which a `>` ends.
+pub fn parse_tag_expr(tokens: &mut VecDeque<Lexeme>) -> Result<Expr, ParseError> {
+    parse_expr_bp(tokens, 0, Closing::Always)
+}
+
+/// An expression in an `<if>` or `<for>` tag, which the `>` ending the line ends:
+/// `<if items.length > 0>`
+pub fn parse_condition(tokens: &mut VecDeque<Lexeme>) -> Result<Expr, ParseError> {
+    parse_expr_bp(tokens, 0, Closing::AtEndOfLine)
+}
+
+/// An event handler: an expression, or an assignment to a state of the widget
+/// such as `count += 1`.
+pub fn parse_handler(tokens: &mut VecDeque<Lexeme>) -> Result<Expr, ParseError> {
+    let target = parse_tag_expr(tokens)?;
+
+    let op = match tokens.front().map(|lexeme| &lexeme.kind) {
+        Some(TokenKind::Equals) => AssignOp::Set,
+        Some(TokenKind::PlusEquals) => AssignOp::Add,
+        Some(TokenKind::MinusEquals) => AssignOp::Sub,
+        Some(TokenKind::AsteriskEquals) => AssignOp::Mul,
+        Some(TokenKind::SlashEquals) => AssignOp::Div,
+        _ => return Ok(target),
+    };
+    tokens.pop_front();
+
+    let value = parse_tag_expr(tokens)?;
+    let span = target.span.to(value.span);
+    Ok(Expr::new(
+        ExprKind::Assign(op, Box::new(target), Box::new(value)),
+        span,
+    ))
+}
+
+fn parse_expr_bp(
+    tokens: &mut VecDeque<Lexeme>,
+    min_bp: u8,
+    closing: Closing,
+) -> Result<Expr, ParseError> {
+    let mut lhs = parse_prefix(tokens, closing)?;
+
+    while let Some(lexeme) = tokens.front() {
+        match lexeme.kind {
+            TokenKind::Dot | TokenKind::OpenSquare | TokenKind::OpenParen => {
+                lhs = parse_postfix(tokens, lhs)?;
+            }
+            TokenKind::GreaterThan if closes_tag(tokens, closing) => break,
+            _ => {
+                let (op, bp) = match binary_op(&lexeme.kind) {
+                    Some(op) => op,
+                    None => break,
+                };
+                // left associative: the right hand side must bind tighter
+                if bp <= min_bp {
+                    break;
+                }
+
+                tokens.pop_front();
+                let rhs = parse_expr_bp(tokens, bp, closing)?;
+                let span = lhs.span.to(rhs.span);
+                lhs = Expr::new(ExprKind::Binary(op, Box::new(lhs), Box::new(rhs)), span);
+            }
+        }
+    }
+
+    Ok(lhs)
+}
+
+fn binary_op(kind: &TokenKind) -> Option<(BinaryOp, u8)> {
+    let op = match kind {
+        TokenKind::Plus => (BinaryOp::Add, ADDITIVE),
+        TokenKind::Minus => (BinaryOp::Sub, ADDITIVE),
+        TokenKind::Asterisk => (BinaryOp::Mul, MULTIPLICATIVE),
+        TokenKind::Slash => (BinaryOp::Div, MULTIPLICATIVE),
+        TokenKind::EqualsEquals => (BinaryOp::Eq, EQUALITY),
+        TokenKind::NotEquals => (BinaryOp::NotEq, EQUALITY),
+        TokenKind::LessThan => (BinaryOp::Lt, RELATIONAL),
+        TokenKind::GreaterThan => (BinaryOp::Gt, RELATIONAL),
+        TokenKind::LessEquals => (BinaryOp::LtEq, RELATIONAL),
+        TokenKind::GreaterEquals => (BinaryOp::GtEq, RELATIONAL),
+        TokenKind::AndAnd => (BinaryOp::And, LOGICAL_AND),
+        TokenKind::OrOr => (BinaryOp::Or, LOGICAL_OR),
+        _ => return None,
+    };
+
+    Some(op)
+}
+
+// whether the `>` in front ends the tag instead of comparing
+fn closes_tag(tokens: &VecDeque<Lexeme>, closing: Closing) -> bool {
+    match closing {
+        Closing::Never => false,
+        Closing::Always => true,
+        Closing::AtEndOfLine => matches!(
+            tokens.get(1).map(|lexeme| &lexeme.kind),
+            None | Some(TokenKind::Newline | TokenKind::End)
+        ),
+    }
+}
+
+fn parse_prefix(tokens: &mut VecDeque<Lexeme>, closing: Closing) -> Result<Expr, ParseError> {
+    let lexeme = take_token(tokens)
+        .ok_or_else(|| ParseError::MissingToken(String::from("an expression")))?;
+    let mut span = lexeme.span();
+
+    let kind = match lexeme.kind {
+        TokenKind::QuotedString(value) => ExprKind::String(value),
+        TokenKind::InterpolatedString(parts) => parse_interpolated(parts, lexeme.start)?,
+        TokenKind::Number(value) => ExprKind::Number(value),
+        TokenKind::Identifier(id) => match id.as_str() {
+            "true" => ExprKind::Bool(true),
+            "false" => ExprKind::Bool(false),
+            "null" => ExprKind::Null,
+            _ => ExprKind::Identifier(id),
+        },
+        TokenKind::Minus => {
+            let operand = parse_expr_bp(tokens, PREFIX, closing)?;
+            span = span.to(operand.span);
+            ExprKind::Unary(UnaryOp::Neg, Box::new(operand))
+        }
+        TokenKind::Bang => {
+            let operand = parse_expr_bp(tokens, PREFIX, closing)?;
+            span = span.to(operand.span);
+            ExprKind::Unary(UnaryOp::Not, Box::new(operand))
+        }
+        TokenKind::OpenParen => {
+            let inner = parse_expr(tokens)?;
+            span = span.to(expect(tokens, TokenKind::CloseParen)?.span());
+            ExprKind::Paren(Box::new(inner))
+        }
+        _ => {
+            return Err(ParseError::UnexpectedToken(
+                lexeme,
+                String::from("an expression"),
+            ))
+        }
+    };
+
+    Ok(Expr::new(kind, span))
+}
+
+fn parse_postfix(tokens: &mut VecDeque<Lexeme>, lhs: Expr) -> Result<Expr, ParseError> {
+    let lexeme = tokens
+        .pop_front()
+        .ok_or_else(|| ParseError::MissingToken(String::from("`.`, `[` or `(`")))?;
+
+    let (kind, end) = match lexeme.kind {
+        TokenKind::Dot => {
+            let (member, member_span) = expect_member(tokens)?;
+            (ExprKind::Member(Box::new(lhs.clone()), member), member_span)
+        }
+        TokenKind::OpenSquare => {
+            let index = parse_expr(tokens)?;
+            let close = expect(tokens, TokenKind::CloseSquare)?;
+            (
+                ExprKind::Index(Box::new(lhs.clone()), Box::new(index)),
+                close.span(),
+            )
+        }
+        TokenKind::OpenParen => {
+            let mut args = Vec::new();
+            while tokens.front().map(|lexeme| &lexeme.kind) != Some(&TokenKind::CloseParen) {
+                args.push(parse_expr(tokens)?);
+
+                match tokens.front().map(|lexeme| &lexeme.kind) {
+                    Some(TokenKind::Comma) => {
+                        tokens.pop_front();
+                    }
+                    _ => break,
+                }
+            }
+            let close = expect(tokens, TokenKind::CloseParen)?;
+            (ExprKind::Call(Box::new(lhs.clone()), args), close.span())
+        }
+        _ => {
+            return Err(ParseError::UnexpectedToken(
+                lexeme,
+                String::from("`.`, `[` or `(`"),
+            ))
+        }
+    };
+
+    Ok(Expr::new(kind, lhs.span.to(end)))
+}
+
+// members may be named like keywords: `controller.state`, `range.in`
+fn expect_member(tokens: &mut VecDeque<Lexeme>) -> Result<(String, Span), ParseError> {
+    match tokens.front().and_then(|lexeme| lexeme.kind.keyword()) {
+        Some(keyword) => {
+            let span = tokens
+                .pop_front()
+                .map(|lexeme| lexeme.span())
+                .unwrap_or_default();
+            Ok((keyword.to_string(), span))
+        }
+        None => expect_identifier(tokens),
+    }
+}
+
+// `string_start` is the offset of the opening quote, so interpolated expressions
+// get spans pointing into the original file
+fn parse_interpolated(parts: Vec<StringPart>, string_start: usize) -> Result<ExprKind, ParseError> {
+    let mut segments = Vec::new();
+
+    for part in parts {
+        let segment = match part {
+            StringPart::Literal { value } => StringSegment::Literal(value),
+            StringPart::Interpolation { source, offset } => {
+                let trimmed = source.trim_start();
+                let base = string_start + offset + source.len() - trimmed.len();
+                let source_span = Span::new(base, base + trimmed.len());
+
+                let tokens = lex(trimmed.trim_end()).map_err(|err| {
+                    let span = Span::new(base + err.span.start, base + err.span.end);
+                    ParseError::InvalidExpression(span, err.message)
+                })?;
+                // an interpolation is a single expression, its line breaks are only whitespace
+                let mut tokens: VecDeque<Lexeme> = tokens
+                    .into_iter()
+                    .filter(|token| {
+                        !matches!(
+                            token.kind,
+                            TokenKind::Newline | TokenKind::Indent | TokenKind::Dedent
+                        )
+                    })
+                    .map(|token| {
+                        Lexeme::new(token.kind, base + token.start, base + token.end, token.line)
+                    })
+                    .collect();
+
+                let expr = parse_expr(&mut tokens)?;
+                if let Some(extra) = tokens.pop_front() {
+                    return Err(ParseError::InvalidExpression(
+                        extra.span().to(source_span),
+                        String::from("unexpected tokens after the interpolated expression"),
+                    ));
+                }
+                StringSegment::Expr(expr)
+            }
+        };
+        segments.push(segment);
+    }
+
+    Ok(ExprKind::Interpolated(segments))
+}
+
+// (+ a (* b c)), to check the shape of a tree without its spans
+#[cfg(test)]
+fn sexpr(expr: &Expr) -> String {
+    match &expr.kind {
+        ExprKind::String(value) => format!("{:?}", value),
+        ExprKind::Interpolated(segments) => format!("(interpolated {})", segments.len()),
+        ExprKind::Number(value) => value.to_string(),
+        ExprKind::Bool(value) => value.to_string(),
+        ExprKind::Null => String::from("null"),
+        ExprKind::Identifier(name) => name.clone(),
+        ExprKind::Member(object, member) => format!("(. {} {})", sexpr(object), member),
+        ExprKind::Index(object, index) => format!("([] {} {})", sexpr(object), sexpr(index)),
+        ExprKind::Call(callee, args) => {
+            let args: Vec<String> = args.iter().map(sexpr).collect();
+            format!("(call {} {})", sexpr(callee), args.join(" "))
+        }
+        ExprKind::Unary(UnaryOp::Neg, operand) => format!("(- {})", sexpr(operand)),
+        ExprKind::Unary(UnaryOp::Not, operand) => format!("(! {})", sexpr(operand)),
+        ExprKind::Binary(op, lhs, rhs) => {
+            let op = match op {
+                BinaryOp::Add => "+",
+                BinaryOp::Sub => "-",
+                BinaryOp::Mul => "*",
+                BinaryOp::Div => "/",
+                BinaryOp::Eq => "==",
+                BinaryOp::NotEq => "!=",
+                BinaryOp::Lt => "<",
+                BinaryOp::Gt => ">",
+                BinaryOp::LtEq => "<=",
+                BinaryOp::GtEq => ">=",
+                BinaryOp::And => "&&",
+                BinaryOp::Or => "||",
+            };
+            format!("({} {} {})", op, sexpr(lhs), sexpr(rhs))
+        }
+        ExprKind::Paren(inner) => format!("(paren {})", sexpr(inner)),
+        ExprKind::Assign(op, target, value) => {
+            let op = match op {
+                AssignOp::Set => "=",
+                AssignOp::Add => "+=",
+                AssignOp::Sub => "-=",
+                AssignOp::Mul => "*=",
+                AssignOp::Div => "/=",
+            };
+            format!("({} {} {})", op, sexpr(target), sexpr(value))
+        }
+    }
+}
+
+#[cfg(test)]
+fn parse_source(src: &str) -> Expr {
+    let mut tokens = VecDeque::from(lex(src).unwrap());
+    let expr = parse_expr(&mut tokens).unwrap();
+    let rest: Vec<_> = tokens.iter().map(|token| &token.kind).collect();
+    assert_eq!(rest, vec![&TokenKind::Newline], "left unparsed");
+    expr
+}
+
+#[test]
+fn multiplication_binds_tighter_than_addition() {
+    assert_eq!(sexpr(&parse_source("a + b * c")), "(+ a (* b c))");
+}
+
+#[test]
+fn subtraction_is_left_associative() {
+    assert_eq!(sexpr(&parse_source("a - b - c")), "(- (- a b) c)");
+}
+
+#[test]
+fn unary_minus_and_parentheses() {
+    assert_eq!(
+        sexpr(&parse_source("-a * (b + 1)")),
+        "(* (- a) (paren (+ b 1)))"
+    );
+}
+
+#[test]
+fn logical_operators_bind_looser_than_comparisons() {
+    assert_eq!(
+        sexpr(&parse_source("!a.done || count + 1 == max && b != null")),
+        "(|| (! (. a done)) (&& (== (+ count 1) max) (!= b null)))"
+    );
+}
+
+#[test]
+fn relational_operators_bind_between_equality_and_addition() {
+    assert_eq!(
+        sexpr(&parse_source("a + 1 >= b == c < d - 1")),
+        "(== (>= (+ a 1) b) (< c (- d 1)))"
+    );
+}
+
+#[test]
+fn not_binds_tighter_than_comparisons() {
+    assert_eq!(
+        sexpr(&parse_source("!done && items.length > 0")),
+        "(&& (! done) (> (. items length) 0))"
+    );
+}
+
+#[test]
+fn a_greater_than_ending_the_line_closes_the_tag() {
+    let mut tokens = VecDeque::from(lex("items.length > 0 > \n").unwrap());
+    let condition = parse_condition(&mut tokens).unwrap();
+
+    assert_eq!(sexpr(&condition), "(> (. items length) 0)");
+    assert_eq!(tokens[0].kind, TokenKind::GreaterThan);
+}
+
+#[test]
+fn widget_tags_compare_in_parentheses() {
+    let mut tokens = VecDeque::from(lex("count > \"many\"").unwrap());
+    assert_eq!(sexpr(&parse_tag_expr(&mut tokens).unwrap()), "count");
+
+    let mut tokens = VecDeque::from(lex("(count > 1) > ").unwrap());
+    assert_eq!(
+        sexpr(&parse_tag_expr(&mut tokens).unwrap()),
+        "(paren (> count 1))"
+    );
+}
+
+#[test]
+fn postfix_chains_bind_tightest() {
+    assert_eq!(
+        sexpr(&parse_source(
+            "controller.history[index].format(2, true) + \"!\""
+        )),
+        "(+ (call (. ([] (. controller history) index) format) 2 true) \"!\")"
+    );
+}
+
+#[test]
+fn members_may_be_named_like_keywords() {
+    assert_eq!(
+        sexpr(&parse_source("controller.state + range.in.widget")),
+        "(+ (. controller state) (. (. range in) widget))"
+    );
+}
+
+#[test]
+fn expressions_span_their_whole_source() {
+    let expr = parse_source("-controller.history[index] * 2");
+
+    assert_eq!(expr.span, Span::new(0, 30));
+}
+
+#[test]
+fn interpolated_expressions_point_into_the_string() {
+    let expr = parse_source("\"Count: ${ controller.counter }\"");
+
+    match expr.kind {
+        ExprKind::Interpolated(segments) => match &segments[1] {
+            StringSegment::Expr(inner) => assert_eq!(inner.span, Span::new(11, 29)),
+            other => panic!("{:?} should be an expression", other),
+        },
+        other => panic!("{:?} should be interpolated", other),
+    }
+}
+
+#[test]
+fn handlers_may_assign() {
+    let mut tokens = VecDeque::from(lex("count += step * 2").unwrap());
+    let handler = parse_handler(&mut tokens).unwrap();
+
+    assert_eq!(sexpr(&handler), "(+= count (* step 2))");
+    assert_eq!(handler.span, Span::new(0, 17));
+}
+
+#[test]
+fn dangling_operator_is_an_error() {
+    let mut tokens = VecDeque::from(lex("a +").unwrap());
+
+    assert!(parse_expr(&mut tokens).is_err());
+}
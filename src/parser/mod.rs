@@ -1 +1,4 @@
+pub mod ast_struct;
+pub mod expression;
+#[allow(clippy::module_inception)]
 pub mod parser;
@@ -1,136 +1,1074 @@
-use crate::lexer::token_struct::{Token as Lexeme, TokenKind};
+use super::{
+    ast_struct::{
+        Attribute, Branch, Comment, ErrorNode, EventBinding, ForNode, IfNode, Import, Item, Node,
+        Param, Prop, Span, StateDecl, StyleProp, TextNode, TrailingComments, Widget, WidgetDecl,
+    },
+    expression::{parse_condition, parse_expr, parse_handler, parse_tag_expr},
+};
+use crate::{
+    diagnostics::diagnostic::Diagnostic,
+    guard_clause,
+    lexer::token_struct::{Token as Lexeme, TokenKind},
+};
 use anyhow::Result;
 use std::{collections::VecDeque, fmt::Debug};
 
+// `<for item in items key:item.id>`, the only option a loop takes
+const KEY_OPTION: &str = "key";
+
+// `state count: int = 0`, a keyword only where a declaration's line starts, so
+// that `controller.state` stays a name
+const STATE_KEYWORD: &str = "state";
+
+// `void Function(int index)`, the Dart function type
+const FUNCTION_TYPE: &str = "Function";
+
 #[derive(Debug)]
 pub enum ParseError {
-    UnexpectedToken(String),
-    MissingToken,
-    InvalidExpression,
+    // the token found and a description of what was expected instead
+    UnexpectedToken(Lexeme, String),
+    // what was expected when the input ran out
+    MissingToken(String),
+    InvalidExpression(Span, String),
+    // an `<else>` tag that doesn't follow an `<if>` block
+    DanglingElse(Span),
+    // a `state` line below the first widget of a declaration's body
+    LateState(Span),
 }
 
-enum Token {
-    Widget,
+impl ParseError {
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            ParseError::UnexpectedToken(found, _) => Some(found.span()),
+            ParseError::MissingToken(_) => None,
+            ParseError::InvalidExpression(span, _)
+            | ParseError::DanglingElse(span)
+            | ParseError::LateState(span) => Some(*span),
+        }
+    }
+
+    pub fn to_diagnostic(&self, file: &str, source: &str) -> Diagnostic {
+        match self {
+            ParseError::UnexpectedToken(found, expected) => Diagnostic::error(
+                file,
+                found.span(),
+                &format!("expected {}, found {}", expected, found.kind.describe()),
+            )
+            .with_label(found.span(), &format!("expected {} here", expected)),
+            ParseError::MissingToken(expected) => {
+                let end = Span::new(source.len(), source.len());
+                Diagnostic::error(
+                    file,
+                    end,
+                    &format!("expected {}, found end of file", expected),
+                )
+            }
+            ParseError::InvalidExpression(span, message) => Diagnostic::error(file, *span, message),
+            ParseError::DanglingElse(span) => {
+                Diagnostic::error(file, *span, "`<else>` without a preceding `<if>`")
+                    .with_note("`<else>` goes on the line after the children of `<if>` or `<else if>`, at the same indentation")
+            }
+            ParseError::LateState(span) => {
+                Diagnostic::error(file, *span, "`state` after the body of the widget")
+                    .with_note("states are declared first, on the lines right under `widget`")
+            }
+        }
+    }
 }
 
+/// A parse tree, complete or not, and every error found while building it.
 #[derive(Debug)]
-pub struct Widget {
-    name: String,
-    children: Vec<Widget>,
+pub struct ParsedProgram {
+    pub items: Vec<Item>,
+    pub errors: Vec<ParseError>,
 }
 
-pub fn parse_program(tokens: &mut VecDeque<Lexeme>) -> Result<Vec<Widget>, ParseError> {
-    let mut result = Vec::new();
+impl ParsedProgram {
+    pub fn into_result(self) -> Result<Vec<Item>, Vec<ParseError>> {
+        guard_clause!(!self.errors.is_empty(), Err(self.errors));
+        Ok(self.items)
+    }
+}
 
-    let tk = tokens.front().unwrap();
-    println!("tk: {:?}", tk);
+/// Parses the whole token stream. A broken item or node doesn't stop the parser:
+/// it is replaced by an `Error` placeholder and parsing resumes on the next line
+/// at or below its indentation.
+pub fn parse_program(tokens: &mut VecDeque<Lexeme>) -> ParsedProgram {
+    let mut items = Vec::new();
+    let mut errors = Vec::new();
+    // the lexer leaves the comments after the last item on the end of the last line
+    let trailing = match tokens.back_mut() {
+        Some(last) if matches!(last.kind, TokenKind::Newline | TokenKind::Dedent) => {
+            std::mem::take(&mut last.trivia)
+        }
+        _ => Vec::new(),
+    };
 
     while let Some(lexeme) = tokens.front() {
-        match lexeme.kind {
-            TokenKind::WidgetKW => {}
-            TokenKind::LessThan => {
-                let widget = parse_widget(tokens, 0)?;
-                result.push(widget);
+        // the line break ending a file of nothing but comments
+        if lexeme.kind == TokenKind::Newline && tokens.len() == 1 {
+            tokens.pop_front();
+            continue;
+        }
+
+        let start = lexeme.span();
+        let item = match lexeme.kind {
+            TokenKind::ImportKW => parse_import(tokens).map(Item::Import),
+            TokenKind::WidgetKW => parse_widget_decl(tokens, &mut errors).map(Item::WidgetDecl),
+            TokenKind::LessThan => parse_widget(tokens, &mut errors).map(Item::Widget),
+            _ => Err(unexpected(tokens, "`import`, `widget` or `<`")),
+        };
+
+        match item {
+            Ok(item) => items.push(item),
+            Err(err) => {
+                let span = synchronize(tokens, start, &err);
+                errors.push(err);
+                items.push(Item::Error(ErrorNode { span }));
             }
-            TokenKind::Indentation(indent) => {
-                tokens.pop_front();
-                let widget = parse_widget(tokens, indent)?;
-                result.push(widget);
+        }
+    }
+
+    if let (Some(first), Some(last)) = (trailing.first(), trailing.last()) {
+        let span = first.span.to(last.span);
+        items.push(Item::Comments(TrailingComments {
+            comments: trailing,
+            span,
+        }));
+    }
+
+    ParsedProgram { items, errors }
+}
+
+// Skips the rest of a broken line and the block indented under it, up to the next
+// line at the same level or the end of the enclosing block. Returns the span of what
+// was given up on.
+fn synchronize(tokens: &mut VecDeque<Lexeme>, start: Span, err: &ParseError) -> Span {
+    let mut span = err.span().map_or(start, |span| start.to(span));
+    let mut depth = 0;
+
+    while let Some(lexeme) = tokens.front() {
+        let line_ended = match lexeme.kind {
+            TokenKind::Dedent if depth == 0 => break,
+            TokenKind::Dedent => {
+                depth -= 1;
+                depth == 0
             }
+            TokenKind::Indent => {
+                depth += 1;
+                false
+            }
+            TokenKind::Newline => depth == 0,
             _ => {
-                return Err(ParseError::UnexpectedToken(format!(
-                    "parse_program {:?}",
-                    lexeme
-                )))
+                span = span.to(lexeme.span());
+                false
             }
+        };
+        tokens.pop_front();
+
+        // the broken line is over, unless a block indented under it follows
+        let next = tokens.front().map(|lexeme| &lexeme.kind);
+        if line_ended && next != Some(&TokenKind::Indent) {
+            break;
         }
     }
-    Ok(result)
+
+    span
+}
+
+fn parse_import(tokens: &mut VecDeque<Lexeme>) -> Result<Import, ParseError> {
+    let keyword = expect(tokens, TokenKind::ImportKW)?;
+    let (path, _) = expect_string(tokens)?;
+    let semicolon = expect(tokens, TokenKind::Semicolon)?;
+    expect_end_of_line(tokens)?;
+
+    Ok(Import {
+        path,
+        span: keyword.span().to(semicolon.span()),
+        comments: keyword.trivia,
+    })
 }
 
-fn parse_children(
+fn parse_widget_decl(
     tokens: &mut VecDeque<Lexeme>,
-    indentation: usize,
-) -> Result<Vec<Widget>, ParseError> {
-    let mut result = Vec::new();
+    errors: &mut Vec<ParseError>,
+) -> Result<WidgetDecl, ParseError> {
+    let keyword = expect(tokens, TokenKind::WidgetKW)?;
+    let (name, _) = expect_identifier(tokens)?;
+
+    expect(tokens, TokenKind::OpenParen)?;
+    let mut params = Vec::new();
 
     while let Some(lexeme) = tokens.front() {
         match lexeme.kind {
-            TokenKind::Indentation(indent) => {
-                tokens.pop_front();
-                let widget = parse_widget(tokens, indent)?;
-                result.push(widget);
-            }
-            _ => {
-                return Err(ParseError::UnexpectedToken(format!(
-                    "parse_children {:?}",
-                    lexeme
-                )))
+            TokenKind::CloseParen => break,
+            TokenKind::Identifier(_) => {
+                params.push(parse_param(tokens)?);
+
+                if tokens.front().map(|lexeme| &lexeme.kind) == Some(&TokenKind::Comma) {
+                    tokens.pop_front();
+                }
             }
+            _ => return Err(unexpected(tokens, "a parameter or `)`")),
         }
     }
 
-    Ok(result)
+    let close = expect(tokens, TokenKind::CloseParen)?;
+    expect_end_of_line(tokens)?;
+
+    let mut states = Vec::new();
+    let body = parse_lines(tokens, errors, Some(&mut states))?;
+
+    Ok(WidgetDecl {
+        name,
+        params,
+        states,
+        body,
+        span: keyword.span().to(close.span()),
+        comments: keyword.trivia,
+    })
 }
 
-fn parse_widget(tokens: &mut VecDeque<Lexeme>, indentation: usize) -> Result<Widget, ParseError> {
-    let mut name: Option<String> = Some(String::from("None"));
-    let mut widget = None;
+fn parse_param(tokens: &mut VecDeque<Lexeme>) -> Result<Param, ParseError> {
+    let (name, name_span) = expect_identifier(tokens)?;
+    expect(tokens, TokenKind::Colon)?;
+    let (ty, ty_span) = parse_type(tokens)?;
 
-    while let Some(lexeme) = tokens.front() {
-        let lexeme_kind = lexeme.kind.clone();
+    Ok(Param {
+        name,
+        ty,
+        span: name_span.to(ty_span),
+    })
+}
+
+// state count: int = 0
+fn parse_state(tokens: &mut VecDeque<Lexeme>) -> Result<StateDecl, ParseError> {
+    let keyword = expect(tokens, TokenKind::from(STATE_KEYWORD))?;
+    let (name, _) = expect_identifier(tokens)?;
+    expect(tokens, TokenKind::Colon)?;
+    let (ty, _) = parse_type(tokens)?;
+    expect(tokens, TokenKind::Equals)?;
+    let init = parse_expr(tokens)?;
+    let span = keyword.span().to(init.span);
+    expect_end_of_line(tokens)?;
+
+    Ok(StateDecl {
+        name,
+        ty,
+        init,
+        comments: keyword.trivia,
+        span,
+    })
+}
+
+// Foo, List<Foo>, Map<String, List<Foo>>, Foo?, void Function(int index)?
+fn parse_type(tokens: &mut VecDeque<Lexeme>) -> Result<(String, Span), ParseError> {
+    let (mut ty, mut span) = parse_named_type(tokens)?;
+
+    // the return type comes first: `void Function()`, `int Function() Function()`
+    while tokens.front().map(|lexeme| &lexeme.kind) == Some(&TokenKind::from(FUNCTION_TYPE)) {
+        let (function, function_span) = parse_named_type(tokens)?;
+        ty += &format!(" {}", function);
+        span = span.to(function_span);
+    }
+
+    Ok((ty, span))
+}
+
+// A type up to its `?`, with its type arguments or, for `Function`, its parameters
+fn parse_named_type(tokens: &mut VecDeque<Lexeme>) -> Result<(String, Span), ParseError> {
+    let (mut ty, mut span) = expect_identifier(tokens)?;
 
-        match lexeme_kind {
-            TokenKind::LessThan => {
+    if tokens.front().map(|lexeme| &lexeme.kind) == Some(&TokenKind::LessThan) {
+        tokens.pop_front();
+        let mut args = vec![parse_type(tokens)?.0];
+        while tokens.front().map(|lexeme| &lexeme.kind) == Some(&TokenKind::Comma) {
+            tokens.pop_front();
+            args.push(parse_type(tokens)?.0);
+        }
+        let close = expect(tokens, TokenKind::GreaterThan)?;
+
+        ty += &format!("<{}>", args.join(", "));
+        span = span.to(close.span());
+    }
+
+    if ty == FUNCTION_TYPE
+        && tokens.front().map(|lexeme| &lexeme.kind) == Some(&TokenKind::OpenParen)
+    {
+        tokens.pop_front();
+        let mut params = Vec::new();
+        while tokens.front().map(|lexeme| &lexeme.kind) != Some(&TokenKind::CloseParen) {
+            if !params.is_empty() {
+                expect(tokens, TokenKind::Comma)?;
+            }
+            // the parameter's name is optional: `Function(int)` or `Function(int index)`
+            let (mut param, _) = parse_type(tokens)?;
+            if let Some(Lexeme {
+                kind: TokenKind::Identifier(name),
+                ..
+            }) = tokens.front()
+            {
+                param += &format!(" {}", name);
                 tokens.pop_front();
             }
-            TokenKind::GreaterThan => {
-                let name_clone = name.clone().unwrap();
-                if name_clone != "" {
-                    let name = name_clone;
-
-                    let mut children = Vec::new();
-
-                    while let Some(lexeme) = tokens.front() {
-                        match lexeme.kind {
-                            TokenKind::Indentation(indent) => {
-                                tokens.pop_front();
-                                if indent > indentation {
-                                    children
-                                        .append(&mut parse_children(tokens, indentation).unwrap());
-                                } else {
-                                    break;
-                                }
-                            }
-                            _ => {
-                                break;
-                            }
-                        }
-                    }
+            params.push(param);
+        }
+        let close = expect(tokens, TokenKind::CloseParen)?;
 
-                    widget = Some(Widget { name, children });
-                } else {
-                    return Err(ParseError::InvalidExpression);
+        ty += &format!("({})", params.join(", "));
+        span = span.to(close.span());
+    }
+
+    if tokens.front().map(|lexeme| &lexeme.kind) == Some(&TokenKind::Question) {
+        let question = expect(tokens, TokenKind::Question)?;
+        ty += "?";
+        span = span.to(question.span());
+    }
+
+    Ok((ty, span))
+}
+
+// The lines indented under the current one, if any: `Indent (node)+ Dedent`
+fn parse_block(
+    tokens: &mut VecDeque<Lexeme>,
+    errors: &mut Vec<ParseError>,
+) -> Result<Vec<Node>, ParseError> {
+    parse_lines(tokens, errors, None)
+}
+
+// A block whose `state` lines go to `states`, when they are allowed there
+fn parse_lines(
+    tokens: &mut VecDeque<Lexeme>,
+    errors: &mut Vec<ParseError>,
+    mut states: Option<&mut Vec<StateDecl>>,
+) -> Result<Vec<Node>, ParseError> {
+    let mut result = Vec::new();
+    guard_clause!(
+        tokens.front().map(|lexeme| &lexeme.kind) != Some(&TokenKind::Indent),
+        Ok(result)
+    );
+    tokens.pop_front();
+
+    while let Some(lexeme) = tokens.front() {
+        if lexeme.kind == TokenKind::Dedent {
+            tokens.pop_front();
+            break;
+        }
+
+        let start = lexeme.span();
+        let is_state = matches!(&lexeme.kind, TokenKind::Identifier(name) if name == STATE_KEYWORD);
+        if let (true, Some(states)) = (is_state, states.as_deref_mut()) {
+            if !result.is_empty() {
+                errors.push(ParseError::LateState(start));
+            }
+            match parse_state(tokens) {
+                Ok(state) => states.push(state),
+                Err(err) => {
+                    synchronize(tokens, start, &err);
+                    errors.push(err);
                 }
             }
-            TokenKind::Identifier(id) => {
-                tokens.pop_front();
-                name = Some(id);
+            continue;
+        }
+
+        match parse_node(tokens, errors) {
+            Ok(node) => result.push(node),
+            Err(err) => {
+                let span = synchronize(tokens, start, &err);
+                errors.push(err);
+                result.push(Node::Error(ErrorNode { span }));
             }
-            TokenKind::Indentation(indent) => {
-                break;
+        }
+    }
+
+    Ok(result)
+}
+
+fn parse_node(
+    tokens: &mut VecDeque<Lexeme>,
+    errors: &mut Vec<ParseError>,
+) -> Result<Node, ParseError> {
+    match tokens.front().map(|lexeme| &lexeme.kind) {
+        Some(TokenKind::QuotedString(_) | TokenKind::InterpolatedString(_)) => {
+            let comments = tokens
+                .front()
+                .map(|lexeme| lexeme.trivia.clone())
+                .unwrap_or_default();
+            let content = parse_expr(tokens)?;
+            let span = content.span;
+            expect_end_of_line(tokens)?;
+            Ok(Node::Text(TextNode {
+                content,
+                comments,
+                span,
+            }))
+        }
+        Some(TokenKind::LessThan) => match tokens.get(1).map(|lexeme| &lexeme.kind) {
+            Some(TokenKind::IfKW) => Ok(Node::If(parse_if(tokens, errors)?)),
+            Some(TokenKind::ForKW) => Ok(Node::For(parse_for(tokens, errors)?)),
+            Some(TokenKind::ElseKW) => {
+                let open = expect(tokens, TokenKind::LessThan)?;
+                let keyword = expect(tokens, TokenKind::ElseKW)?;
+                Err(ParseError::DanglingElse(open.span().to(keyword.span())))
             }
-            _ => {
-                return Err(ParseError::UnexpectedToken(format!(
-                    "parse_widget {:?}",
-                    lexeme
-                )))
+            _ => Ok(Node::Widget(parse_widget(tokens, errors)?)),
+        },
+        _ => Ok(Node::Widget(parse_widget(tokens, errors)?)),
+    }
+}
+
+// `<if cond>` and its children, then the `<else if cond>` and `<else>` lines that
+// follow at the same level
+fn parse_if(
+    tokens: &mut VecDeque<Lexeme>,
+    errors: &mut Vec<ParseError>,
+) -> Result<IfNode, ParseError> {
+    let open = expect(tokens, TokenKind::LessThan)?;
+    expect(tokens, TokenKind::IfKW)?;
+    let first = parse_branch(tokens, errors, open.span(), Vec::new(), true)?;
+    let span = first.span;
+
+    let mut branches = vec![first];
+    while branches
+        .last()
+        .is_some_and(|branch| branch.condition.is_some())
+        && tokens.front().map(|lexeme| &lexeme.kind) == Some(&TokenKind::LessThan)
+        && tokens.get(1).map(|lexeme| &lexeme.kind) == Some(&TokenKind::ElseKW)
+    {
+        let open = expect(tokens, TokenKind::LessThan)?;
+        expect(tokens, TokenKind::ElseKW)?;
+        let has_condition = tokens.front().map(|lexeme| &lexeme.kind) == Some(&TokenKind::IfKW);
+        if has_condition {
+            tokens.pop_front();
+        }
+        branches.push(parse_branch(
+            tokens,
+            errors,
+            open.span(),
+            open.trivia,
+            has_condition,
+        )?);
+    }
+
+    Ok(IfNode {
+        branches,
+        comments: open.trivia,
+        span,
+    })
+}
+
+// `<for item, index in iterable key:expr>` and its children
+fn parse_for(
+    tokens: &mut VecDeque<Lexeme>,
+    errors: &mut Vec<ParseError>,
+) -> Result<ForNode, ParseError> {
+    let open = expect(tokens, TokenKind::LessThan)?;
+    expect(tokens, TokenKind::ForKW)?;
+    let (item, _) = expect_identifier(tokens)?;
+    let mut index = None;
+    if tokens.front().map(|lexeme| &lexeme.kind) == Some(&TokenKind::Comma) {
+        tokens.pop_front();
+        index = Some(expect_identifier(tokens)?.0);
+    }
+    expect(tokens, TokenKind::InKW)?;
+    let iterable = parse_condition(tokens)?;
+
+    let mut key = None;
+    let is_key = matches!(
+        tokens.front().map(|lexeme| &lexeme.kind),
+        Some(TokenKind::Identifier(name)) if name == KEY_OPTION
+    );
+    if is_key && tokens.get(1).map(|lexeme| &lexeme.kind) == Some(&TokenKind::Colon) {
+        tokens.pop_front();
+        tokens.pop_front();
+        key = Some(parse_condition(tokens)?);
+    }
+
+    let close = expect(tokens, TokenKind::GreaterThan)?;
+    expect_end_of_line(tokens)?;
+    let children = parse_block(tokens, errors)?;
+
+    Ok(ForNode {
+        item,
+        index,
+        iterable,
+        key,
+        children,
+        span: open.span().to(close.span()),
+        comments: open.trivia,
+    })
+}
+
+// the rest of a branch's tag, from its condition, then its children
+fn parse_branch(
+    tokens: &mut VecDeque<Lexeme>,
+    errors: &mut Vec<ParseError>,
+    open: Span,
+    comments: Vec<Comment>,
+    has_condition: bool,
+) -> Result<Branch, ParseError> {
+    let condition = match has_condition {
+        true => Some(parse_condition(tokens)?),
+        false => None,
+    };
+    let close = expect(tokens, TokenKind::GreaterThan)?;
+    expect_end_of_line(tokens)?;
+    let children = parse_block(tokens, errors)?;
+
+    Ok(Branch {
+        condition,
+        children,
+        comments,
+        span: open.to(close.span()),
+    })
+}
+
+fn parse_widget(
+    tokens: &mut VecDeque<Lexeme>,
+    errors: &mut Vec<ParseError>,
+) -> Result<Widget, ParseError> {
+    let open = expect(tokens, TokenKind::LessThan)?;
+    let (name, _) = expect_identifier(tokens)?;
+
+    let mut style = Vec::new();
+    if tokens.front().map(|lexeme| &lexeme.kind) == Some(&TokenKind::OpenSquare) {
+        style = parse_style(tokens)?;
+    }
+
+    let mut attributes = Vec::new();
+    let mut props = Vec::new();
+    let mut events = Vec::new();
+    while let Some(lexeme) = tokens.front() {
+        match lexeme.kind {
+            TokenKind::GreaterThan => break,
+            TokenKind::At => events.push(parse_event(tokens)?),
+            TokenKind::Identifier(_) => match tokens.get(1).map(|lexeme| &lexeme.kind) {
+                Some(TokenKind::Colon) => props.push(parse_prop(tokens)?),
+                _ => attributes.push(parse_attribute(tokens)?),
+            },
+            _ => return Err(unexpected(tokens, "an attribute, a prop, an event or `>`")),
+        }
+    }
+
+    let close = expect(tokens, TokenKind::GreaterThan)?;
+
+    let content = match tokens.front().map(|lexeme| &lexeme.kind) {
+        None | Some(TokenKind::Newline) => None,
+        Some(_) => Some(parse_expr(tokens)?),
+    };
+    expect_end_of_line(tokens)?;
+
+    let children = parse_block(tokens, errors)?;
+
+    Ok(Widget {
+        name,
+        style,
+        attributes,
+        props,
+        events,
+        content,
+        children,
+        span: open.span().to(close.span()),
+        comments: open.trivia,
+    })
+}
+
+fn parse_attribute(tokens: &mut VecDeque<Lexeme>) -> Result<Attribute, ParseError> {
+    let (name, name_span) = expect_identifier(tokens)?;
+    expect(tokens, TokenKind::Equals)?;
+    let (value, value_span) = expect_string(tokens)?;
+
+    Ok(Attribute {
+        name,
+        value,
+        span: name_span.to(value_span),
+    })
+}
+
+fn parse_prop(tokens: &mut VecDeque<Lexeme>) -> Result<Prop, ParseError> {
+    let (name, name_span) = expect_identifier(tokens)?;
+    expect(tokens, TokenKind::Colon)?;
+    let value = parse_tag_expr(tokens)?;
+    let span = name_span.to(value.span);
+
+    Ok(Prop { name, value, span })
+}
+
+fn parse_event(tokens: &mut VecDeque<Lexeme>) -> Result<EventBinding, ParseError> {
+    let at = expect(tokens, TokenKind::At)?;
+    let (name, _) = expect_identifier(tokens)?;
+    expect(tokens, TokenKind::Colon)?;
+    let handler = parse_handler(tokens)?;
+    let span = at.span().to(handler.span);
+
+    Ok(EventBinding {
+        name,
+        handler,
+        span,
+    })
+}
+
+fn parse_style(tokens: &mut VecDeque<Lexeme>) -> Result<Vec<StyleProp>, ParseError> {
+    expect(tokens, TokenKind::OpenSquare)?;
+    let mut style = Vec::new();
+
+    while let Some(lexeme) = tokens.front() {
+        match lexeme.kind {
+            TokenKind::CloseSquare => break,
+            TokenKind::Identifier(_) => {
+                let (key, key_span) = expect_identifier(tokens)?;
+                expect(tokens, TokenKind::Colon)?;
+
+                let value = match take_token(tokens) {
+                    Some(Lexeme {
+                        kind: TokenKind::StyleValue(value),
+                        end,
+                        ..
+                    }) => (value, end),
+                    Some(lexeme) => {
+                        return Err(ParseError::UnexpectedToken(
+                            lexeme,
+                            String::from("a style value"),
+                        ))
+                    }
+                    None => return Err(ParseError::MissingToken(String::from("a style value"))),
+                };
+
+                style.push(StyleProp {
+                    key,
+                    value: value.0,
+                    span: Span::new(key_span.start, value.1),
+                });
             }
+            _ => return Err(unexpected(tokens, "a style key or `]`")),
         }
     }
 
-    return Ok(widget.unwrap_or(Widget {
-        name: String::from("None"),
-        children: vec![],
-    }));
+    expect(tokens, TokenKind::CloseSquare)?;
+    Ok(style)
+}
+
+// the token at the front of `tokens` is not what `expected` describes
+fn unexpected(tokens: &mut VecDeque<Lexeme>, expected: &str) -> ParseError {
+    match take_token(tokens) {
+        Some(lexeme) => ParseError::UnexpectedToken(lexeme, expected.to_string()),
+        None => ParseError::MissingToken(expected.to_string()),
+    }
+}
+
+// Like `pop_front`, but the tokens laying out lines are left in place so error
+// recovery can resume on the next line.
+pub(super) fn take_token(tokens: &mut VecDeque<Lexeme>) -> Option<Lexeme> {
+    match tokens.front()?.kind {
+        TokenKind::Newline | TokenKind::Indent | TokenKind::Dedent => tokens.front().cloned(),
+        _ => tokens.pop_front(),
+    }
+}
+
+// the lexer ends every non-blank line with a `Newline`, even the last one
+fn expect_end_of_line(tokens: &mut VecDeque<Lexeme>) -> Result<(), ParseError> {
+    match tokens.front() {
+        Some(Lexeme {
+            kind: TokenKind::Newline,
+            ..
+        }) => {
+            tokens.pop_front();
+            Ok(())
+        }
+        Some(_) => Err(unexpected(tokens, "a new line")),
+        None => Err(ParseError::MissingToken(String::from("a new line"))),
+    }
+}
+
+pub(super) fn expect(tokens: &mut VecDeque<Lexeme>, kind: TokenKind) -> Result<Lexeme, ParseError> {
+    match take_token(tokens) {
+        Some(lexeme) if lexeme.kind == kind => Ok(lexeme),
+        Some(lexeme) => Err(ParseError::UnexpectedToken(lexeme, kind.describe())),
+        None => Err(ParseError::MissingToken(kind.describe())),
+    }
+}
+
+pub(super) fn expect_identifier(
+    tokens: &mut VecDeque<Lexeme>,
+) -> Result<(String, Span), ParseError> {
+    match take_token(tokens) {
+        Some(Lexeme {
+            kind: TokenKind::Identifier(id),
+            start,
+            end,
+            ..
+        }) => Ok((id, Span::new(start, end))),
+        Some(lexeme) => Err(ParseError::UnexpectedToken(
+            lexeme,
+            String::from("an identifier"),
+        )),
+        None => Err(ParseError::MissingToken(String::from("an identifier"))),
+    }
+}
+
+fn expect_string(tokens: &mut VecDeque<Lexeme>) -> Result<(String, Span), ParseError> {
+    match take_token(tokens) {
+        Some(Lexeme {
+            kind: TokenKind::QuotedString(value),
+            start,
+            end,
+            ..
+        }) => Ok((value, Span::new(start, end))),
+        Some(lexeme) => Err(ParseError::UnexpectedToken(
+            lexeme,
+            String::from("a plain string"),
+        )),
+        None => Err(ParseError::MissingToken(String::from("a plain string"))),
+    }
+}
+
+#[test]
+fn unexpected_token_diagnostic_points_at_the_token() {
+    let source = "<Column>\n  <Text \"hi\">";
+    let tokens = crate::lexer::lexer::lex(source).unwrap();
+    let err = parse_program(&mut VecDeque::from(tokens)).errors.remove(0);
+
+    assert_eq!(
+        err.to_diagnostic("app.flutter", source).render(source),
+        concat!(
+            "error: expected an attribute, a prop, an event or `>`, found string\n",
+            " --> app.flutter:2:9\n",
+            "  |\n",
+            "2 |   <Text \"hi\">\n",
+            "  |         ^^^^ expected an attribute, a prop, an event or `>` here\n",
+        )
+    );
+}
+
+#[cfg(test)]
+fn parse_source(source: &str) -> ParsedProgram {
+    let tokens = crate::lexer::lexer::lex(source).unwrap();
+    parse_program(&mut VecDeque::from(tokens))
+}
+
+#[test]
+fn every_broken_line_is_reported() {
+    let program = parse_source(concat!(
+        "<Column>\n",
+        "  <Text \"a\">\n",
+        "  <Text> \"fine\"\n",
+        "  <Row>\n",
+        "    <Icon size:>\n",
+        "    <Text> 1 +\n",
+        "  <Text> \"also fine\"\n",
+        "  <Image[w]>\n",
+        "  <=>\n",
+    ));
+
+    assert_eq!(program.errors.len(), 5, "{:#?}", program.errors);
+}
+
+#[test]
+fn broken_nodes_become_error_placeholders() {
+    let program = parse_source("<Column>\n  <Text \"a\">\n    <Icon>\n  <Text> \"fine\"");
+
+    let column = match &program.items[..] {
+        [Item::Widget(column)] => column,
+        other => panic!("{:#?} should be a single widget", other),
+    };
+    match &column.children[..] {
+        [Node::Error(error), Node::Widget(text)] => {
+            assert_eq!(error.span, Span::new(11, 32));
+            assert_eq!(text.name, "Text");
+        }
+        other => panic!("{:#?} should be an error then a widget", other),
+    }
+}
+
+#[test]
+fn broken_items_do_not_stop_the_next_ones() {
+    let program = parse_source("import \"a.dart\"\n<Column>\n  <Text> \"fine\"");
+
+    assert_eq!(program.errors.len(), 1);
+    assert!(matches!(
+        &program.items[..],
+        [Item::Error(_), Item::Widget(_)]
+    ));
+}
+
+// Widget { Button { Text, Text }, Button { Text, Text } }
+#[cfg(test)]
+fn shape(nodes: &[Node]) -> String {
+    let shapes: Vec<String> = nodes
+        .iter()
+        .map(|node| match node {
+            Node::Widget(widget) if widget.children.is_empty() => widget.name.clone(),
+            Node::Widget(widget) => format!("{} {{ {} }}", widget.name, shape(&widget.children)),
+            Node::Text(_) => String::from("\"\""),
+            Node::If(node) => {
+                let branches: Vec<String> = node
+                    .branches
+                    .iter()
+                    .map(|branch| format!("{{ {} }}", shape(&branch.children)))
+                    .collect();
+                format!("if {}", branches.join(" else "))
+            }
+            Node::For(node) => format!("for {{ {} }}", shape(&node.children)),
+            Node::Error(_) => String::from("?"),
+        })
+        .collect();
+    shapes.join(", ")
+}
+
+#[test]
+fn nest_children_by_indentation() {
+    // the example from doc/tag_identation.pseudo, trailing spaces included
+    let program = parse_source(
+        "<Widget>\n  <Button>\n    <Text> \"+\"\n    <Text> \"plus\"\n  <Button>  \n    <Text> \"-\"\n    <Text> \"minus\"",
+    );
+    let nodes: Vec<Node> = program
+        .into_result()
+        .unwrap()
+        .into_iter()
+        .map(|item| match item {
+            Item::Widget(widget) => Node::Widget(widget),
+            other => panic!("{:?} should be a widget", other),
+        })
+        .collect();
+
+    assert_eq!(
+        shape(&nodes),
+        "Widget { Button { Text, Text }, Button { Text, Text } }"
+    );
+}
+
+#[test]
+fn dedent_closes_several_levels_at_once() {
+    let program = parse_source(
+        "<Column>\n  <Row>\n    <Card>\n      <Icon>\n  <Row>\n\n    <Card>\n<Footer>",
+    );
+    let nodes: Vec<Node> = program
+        .into_result()
+        .unwrap()
+        .into_iter()
+        .filter_map(|item| match item {
+            Item::Widget(widget) => Some(Node::Widget(widget)),
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(
+        shape(&nodes),
+        "Column { Row { Card { Icon } }, Row { Card } }, Footer"
+    );
+}
+
+#[test]
+fn comments_belong_to_the_next_node() {
+    let program = parse_source("// the page\n<Column>\n  <!-- a -->\n  \"x\"\n  <Icon> // b\n");
+    let column = match program.into_result().unwrap().remove(0) {
+        Item::Widget(widget) => widget,
+        other => panic!("{:?} should be a widget", other),
+    };
+    let texts = |comments: &[Comment]| -> Vec<String> {
+        comments
+            .iter()
+            .map(|comment| comment.text.clone())
+            .collect()
+    };
+
+    assert_eq!(texts(&column.comments), vec![" the page"]);
+    match column.children.as_slice() {
+        [Node::Text(text), Node::Widget(icon)] => {
+            assert_eq!(texts(&text.comments), vec![" a "]);
+            // nothing follows `// b`, it is left on the end of the file
+            assert!(icon.comments.is_empty());
+        }
+        other => panic!("unexpected children {:?}", other),
+    }
+}
+
+#[test]
+fn comments_after_the_last_item_trail_the_file() {
+    for (src, should_be) in [
+        ("<Column>\n  <Icon>\n// the end\n", "Widget Comments"),
+        ("// nothing yet\n/* to come */", "Comments"),
+    ] {
+        let items = parse_source(src).into_result().unwrap();
+        let kinds: Vec<&str> = items
+            .iter()
+            .map(|item| match item {
+                Item::Widget(_) => "Widget",
+                Item::Comments(_) => "Comments",
+                _ => "other",
+            })
+            .collect();
+
+        assert_eq!(kinds.join(" "), should_be, "{:?}", src);
+    }
+}
+
+#[test]
+fn else_branches_continue_the_if_above() {
+    let program = parse_source(concat!(
+        "<Column>\n",
+        "  <if loading>\n",
+        "    <Spinner>\n",
+        "  <else if failed>\n",
+        "    <Icon>\n",
+        "    <Text>\n",
+        "  <else>\n",
+        "    <List>\n",
+        "  <if empty>\n",
+        "    <Hint>\n",
+        "  <Footer>\n",
+    ));
+    let column = match program.into_result().unwrap().remove(0) {
+        Item::Widget(widget) => widget,
+        other => panic!("{:?} should be a widget", other),
+    };
+
+    assert_eq!(
+        shape(&column.children),
+        "if { Spinner } else { Icon, Text } else { List }, if { Hint }, Footer"
+    );
+}
+
+#[test]
+fn else_without_if_is_an_error() {
+    let program = parse_source("<Column>\n  <Text>\n  <else>\n    <Icon>\n  <Row>");
+
+    assert!(matches!(
+        program.errors.as_slice(),
+        [ParseError::DanglingElse(span)] if *span == Span::new(20, 25)
+    ));
+}
+
+#[test]
+fn for_binds_an_item_and_an_optional_index() {
+    let program = parse_source(concat!(
+        "<Column>\n",
+        "  <for entry, index in controller.history key:entry.id>\n",
+        "    <Text>\n",
+        "  <for row in rows>\n",
+        "    <Row>\n",
+    ));
+    let column = match program.into_result().unwrap().remove(0) {
+        Item::Widget(widget) => widget,
+        other => panic!("{:?} should be a widget", other),
+    };
+
+    assert_eq!(shape(&column.children), "for { Text }, for { Row }");
+    match column.children.as_slice() {
+        [Node::For(first), Node::For(second)] => {
+            assert_eq!(first.item, "entry");
+            assert_eq!(first.index.as_deref(), Some("index"));
+            assert_eq!(
+                first.key.as_ref().map(|key| key.span),
+                Some(Span::new(55, 63))
+            );
+            assert_eq!(second.index, None);
+            assert_eq!(second.key, None);
+        }
+        other => panic!("unexpected children {:?}", other),
+    }
+}
+
+#[test]
+fn for_only_takes_a_key_option() {
+    let program = parse_source("<Column>\n  <for a in b count:3>\n    <Text>");
+
+    assert_eq!(program.errors.len(), 1, "{:#?}", program.errors);
+}
+
+#[test]
+fn states_are_declared_before_the_body() {
+    let program = parse_source(concat!(
+        "widget Counter(step: int)\n",
+        "  state count: int = step * 2\n",
+        "  state names: Map<String, int> = null\n",
+        "  <Text> \"${count}\"\n",
+    ));
+    let decl = match program.into_result().unwrap().remove(0) {
+        Item::WidgetDecl(decl) => decl,
+        other => panic!("{:?} should be a declaration", other),
+    };
+
+    let states: Vec<(&str, &str)> = decl
+        .states
+        .iter()
+        .map(|state| (state.name.as_str(), state.ty.as_str()))
+        .collect();
+    assert_eq!(
+        states,
+        vec![("count", "int"), ("names", "Map<String, int>")]
+    );
+    assert_eq!(decl.states[0].span, Span::new(28, 55));
+    assert_eq!(shape(&decl.body), "Text");
+}
+
+#[test]
+fn params_take_nullable_and_function_types() {
+    let program = parse_source(concat!(
+        "widget Field(label: String?, onTap: void Function(), onChanged: ValueChanged<int>?, ",
+        "onPick: void Function(int index, String)?, make: Widget Function() Function(bool))\n",
+        "  <Text> \"x\"\n",
+    ));
+    let decl = match program.into_result().unwrap().remove(0) {
+        Item::WidgetDecl(decl) => decl,
+        other => panic!("{:?} should be a declaration", other),
+    };
+
+    let types: Vec<&str> = decl.params.iter().map(|param| param.ty.as_str()).collect();
+    assert_eq!(
+        types,
+        vec![
+            "String?",
+            "void Function()",
+            "ValueChanged<int>?",
+            "void Function(int index, String)?",
+            "Widget Function() Function(bool)",
+        ]
+    );
+    assert_eq!(decl.params[0].span, Span::new(13, 27));
+}
+
+#[test]
+fn states_after_the_body_are_errors() {
+    let program = parse_source(concat!(
+        "widget Counter()\n",
+        "  <Text> \"${count}\"\n",
+        "  state count: int = 0\n",
+    ));
+
+    assert!(
+        matches!(program.errors.as_slice(), [ParseError::LateState(span)] if *span == Span::new(39, 44)),
+        "{:#?}",
+        program.errors
+    );
+}
+
+#[test]
+fn state_is_only_a_keyword_in_declarations() {
+    let program = parse_source(concat!(
+        "widget Status(controller: Controller)\n",
+        "  state shown: bool = true\n",
+        "  <Text> controller.state\n",
+    ));
+    let decl = match program.into_result().unwrap().remove(0) {
+        Item::WidgetDecl(decl) => decl,
+        other => panic!("{:?} should be a declaration", other),
+    };
+
+    assert_eq!(decl.states.len(), 1);
+    match &decl.body[..] {
+        [Node::Widget(text)] => assert!(
+            matches!(&text.content, Some(super::ast_struct::Expr { kind: super::ast_struct::ExprKind::Member(_, member), .. }) if member == "state"),
+            "{:?}",
+            text.content
+        ),
+        other => panic!("unexpected body {:?}", other),
+    }
+}
+
+#[test]
+fn states_only_go_in_declarations() {
+    let program = parse_source("<Column>\n  state count: int = 0\n  <Text>");
+
+    assert_eq!(program.errors.len(), 1, "{:#?}", program.errors);
 }
@@ -0,0 +1,272 @@
+use crate::{
+    config::config::Config,
+    diagnostics::diagnostic::Diagnostic,
+    guard_clause,
+    lexer::{lexer::lex_with, token_struct::Span},
+    parser::{
+        ast_struct::{Import, Item, WidgetDecl},
+        parser::parse_program,
+    },
+};
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// The extension of markup files, which `import` resolves and `build` compiles.
+pub const MARKUP_EXTENSION: &str = "flutter";
+
+#[derive(Debug)]
+pub struct Module {
+    pub path: PathBuf,
+    pub source: String,
+    pub items: Vec<Item>,
+}
+
+impl Module {
+    pub fn imports(&self) -> impl Iterator<Item = &Import> {
+        self.items.iter().filter_map(|item| match item {
+            Item::Import(import) => Some(import),
+            _ => None,
+        })
+    }
+
+    pub fn widget_decls(&self) -> impl Iterator<Item = &WidgetDecl> {
+        self.items.iter().filter_map(|item| match item {
+            Item::WidgetDecl(decl) => Some(decl),
+            _ => None,
+        })
+    }
+}
+
+/// Every markup file reachable from an entry file through `import "x.flutter";`,
+/// keyed by canonical path.
+#[derive(Debug, Default)]
+pub struct ModuleGraph {
+    pub modules: BTreeMap<PathBuf, Module>,
+}
+
+impl ModuleGraph {
+    pub fn get(&self, path: &Path) -> Option<&Module> {
+        self.modules.get(path)
+    }
+
+    /// Widgets a module can reference: its own declarations plus the ones declared
+    /// by the markup files it imports directly, like Dart's non-transitive imports.
+    /// A name declared twice is reported in the module, where it clashes.
+    pub fn visible_widgets(&self, path: &Path) -> Result<HashMap<&str, &WidgetDecl>, Diagnostic> {
+        let module = self.get(path).ok_or_else(|| {
+            let message = format!("{} is not part of the module graph", path.display());
+            Diagnostic::error(&module_file(path), Span::default(), &message)
+        })?;
+
+        // each declaration with where the module sees it from
+        let mut sources = vec![(module, None)];
+        for import in module.imports() {
+            if let Some(target) = resolve_import(module, import)? {
+                sources.extend(self.get(&target).map(|source| (source, Some(import))));
+            }
+        }
+
+        let mut visible = HashMap::new();
+        for (source, import) in sources {
+            for decl in source.widget_decls() {
+                if let Some(first) = visible.insert(decl.name.as_str(), decl) {
+                    let span = import.map_or(decl.span, |import| import.span);
+                    let message = format!("widget `{}` is declared more than once", decl.name);
+                    let mut diagnostic = Diagnostic::error(&module_file(path), span, &message)
+                        .with_note(&format!("declared again in {}", source.path.display()));
+                    // labels can only point into the module itself
+                    if module.widget_decls().any(|own| std::ptr::eq(own, first)) {
+                        diagnostic = diagnostic.with_label(first.span, "first declared here");
+                    }
+                    return Err(diagnostic);
+                }
+            }
+        }
+
+        Ok(visible)
+    }
+}
+
+fn module_file(path: &Path) -> String {
+    path.display().to_string()
+}
+
+/// Reads and parses a markup file. The error lists its problems, one per line
+/// of the form `file:line:column: message`.
+pub fn load_module(path: &Path) -> Result<Module, Vec<String>> {
+    let file = module_file(path);
+    let input =
+        fs::read_to_string(path).map_err(|err| vec![format!("cannot read {}: {}", file, err)])?;
+    let config = Config::discover(path).map_err(|message| vec![message.trim().to_string()])?;
+    let tokens = lex_with(&input, &config.lex_options())
+        .map_err(|err| vec![summarize(&err.to_diagnostic(&file), &input)])?;
+    let items = parse_program(&mut VecDeque::from(tokens))
+        .into_result()
+        .map_err(|errors| {
+            errors
+                .iter()
+                .map(|err| summarize(&err.to_diagnostic(&file, &input), &input))
+                .collect::<Vec<String>>()
+        })?;
+
+    Ok(Module {
+        path: path.to_path_buf(),
+        source: input,
+        items,
+    })
+}
+
+// `lib/card.flutter:3:9: expected `>``, a diagnostic of another file in one line
+fn summarize(diagnostic: &Diagnostic, source: &str) -> String {
+    let before = &source[..diagnostic.span.start.min(source.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+    format!(
+        "{}:{}:{}: {}",
+        diagnostic.file, line, column, diagnostic.message
+    )
+}
+
+/// Returns the canonical path of an imported markup file, relative to the importing
+/// module, or `None` when the import targets plain Dart and is forwarded as is.
+pub fn resolve_import(from: &Module, import: &Import) -> Result<Option<PathBuf>, Diagnostic> {
+    let target = Path::new(&import.path);
+    guard_clause!(
+        target.extension().and_then(|ext| ext.to_str()) != Some(MARKUP_EXTENSION),
+        Ok(None)
+    );
+
+    locate_import(from, import).map(Some)
+}
+
+/// Where any import points to, markup or Dart: next to the importing module, or
+/// in the project's `lib` for its own `package:` imports.
+pub fn locate_import(from: &Module, import: &Import) -> Result<PathBuf, Diagnostic> {
+    let file = module_file(&from.path);
+    let config = Config::discover(&from.path).map_err(|message| {
+        Diagnostic::error(&file, import.span, "cannot read the project configuration")
+            .with_note(message.trim())
+    })?;
+    let dir = from.path.parent().unwrap_or_else(|| Path::new("."));
+    let target = config
+        .package_path(&import.path)
+        .unwrap_or_else(|| dir.join(&import.path));
+
+    target.canonicalize().map_err(|err| {
+        let message = format!("cannot find imported file {:?}", import.path);
+        Diagnostic::error(&file, import.span, &message).with_note(&err.to_string())
+    })
+}
+
+pub fn resolve(entry: &Path) -> Result<ModuleGraph, Diagnostic> {
+    let path = entry.canonicalize().map_err(|err| {
+        let message = format!("cannot find {}", entry.display());
+        Diagnostic::error(&module_file(entry), Span::default(), &message)
+            .with_note(&err.to_string())
+    })?;
+    let module = load_module(&path).map_err(|problems| {
+        let message = format!("cannot load {}", entry.display());
+        problems.iter().fold(
+            Diagnostic::error(&module_file(entry), Span::default(), &message),
+            |diagnostic, problem| diagnostic.with_note(problem),
+        )
+    })?;
+
+    resolve_module(module)
+}
+
+/// The graph of the markup files `entry` imports, directly or not. Problems are
+/// reported in `entry`: the ones of the files it reaches point at the import
+/// leading to them.
+pub fn resolve_module(entry: Module) -> Result<ModuleGraph, Diagnostic> {
+    let mut graph = ModuleGraph::default();
+    let file = module_file(&entry.path);
+
+    // each path with the import of the entry it is reached through
+    let mut pending = Vec::new();
+    for import in entry.imports() {
+        if let Some(target) = resolve_import(&entry, import)? {
+            pending.push((target, import.clone()));
+        }
+    }
+    graph.modules.insert(entry.path.clone(), entry);
+
+    while let Some((path, through)) = pending.pop() {
+        if graph.modules.contains_key(&path) {
+            continue;
+        }
+
+        let failed = |problems: Vec<String>| {
+            let message = format!("cannot compile the imported file {:?}", through.path);
+            problems.iter().fold(
+                Diagnostic::error(&file, through.span, &message),
+                |diagnostic, problem| diagnostic.with_note(problem),
+            )
+        };
+        let module = load_module(&path).map_err(failed)?;
+        for import in module.imports() {
+            let target = resolve_import(&module, import)
+                .map_err(|diagnostic| failed(vec![summarize(&diagnostic, &module.source)]))?;
+            pending.extend(target.map(|target| (target, through.clone())));
+        }
+        graph.modules.insert(path, module);
+    }
+
+    Ok(graph)
+}
+
+#[cfg(test)]
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/imports")
+        .join(name)
+}
+
+#[test]
+fn resolve_follows_markup_imports_through_cycles() {
+    let graph = resolve(&fixture("app.flutter")).unwrap();
+
+    assert_eq!(graph.modules.len(), 2);
+}
+
+#[test]
+fn imported_widgets_are_visible() {
+    let entry = fixture("app.flutter").canonicalize().unwrap();
+    let graph = resolve(&entry).unwrap();
+
+    let mut names: Vec<&str> = graph.visible_widgets(&entry).unwrap().into_keys().collect();
+    names.sort();
+    assert_eq!(names, vec!["App", "CounterView"]);
+}
+
+#[test]
+fn package_imports_point_into_the_project_lib() {
+    let entry = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/package/lib/home.flutter")
+        .canonicalize()
+        .unwrap();
+    let graph = resolve(&entry).unwrap();
+
+    let mut names: Vec<&str> = graph.visible_widgets(&entry).unwrap().into_keys().collect();
+    names.sort();
+    assert_eq!(names, vec!["Home", "ProfileCard"]);
+}
+
+#[test]
+fn missing_import_is_an_error() {
+    let got = resolve(&fixture("missing_import.flutter"));
+
+    assert!(got.is_err(), "{:?} should be an error", got);
+}
+
+#[test]
+fn widget_declared_twice_is_an_error() {
+    let entry = fixture("conflict.flutter").canonicalize().unwrap();
+    let graph = resolve(&entry).unwrap();
+
+    let got = graph.visible_widgets(&entry);
+    assert!(got.is_err(), "{:?} should be an error", got);
+}
@@ -0,0 +1,121 @@
+use crate::{
+    diagnostics::diagnostic::Diagnostic, lexer::token_struct::Token, parser::ast_struct::Item,
+};
+use anyhow::{bail, Result};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+/// Bumped on any change to the JSON shape of tokens or AST nodes, so tools
+/// reading `--format json` output can refuse documents they don't understand.
+pub const SCHEMA_VERSION: u32 = 9;
+
+/// `wdart tokens --format json`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TokensDocument {
+    pub version: u32,
+    pub file: String,
+    pub tokens: Vec<Token>,
+}
+
+/// `wdart ast --format json`, the tree may be partial when there are diagnostics
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AstDocument {
+    pub version: u32,
+    pub file: String,
+    pub items: Vec<Item>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl TokensDocument {
+    pub fn new(file: &str, tokens: Vec<Token>) -> Self {
+        TokensDocument {
+            version: SCHEMA_VERSION,
+            file: file.to_string(),
+            tokens,
+        }
+    }
+}
+
+impl AstDocument {
+    pub fn new(file: &str, items: Vec<Item>, diagnostics: Vec<Diagnostic>) -> Self {
+        AstDocument {
+            version: SCHEMA_VERSION,
+            file: file.to_string(),
+            items,
+            diagnostics,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Versioned {
+    version: u32,
+}
+
+/// Reads a document written by this or an older build with the same schema version.
+pub fn from_json<T: DeserializeOwned>(json: &str) -> Result<T> {
+    let Versioned { version } = serde_json::from_str(json)?;
+    if version != SCHEMA_VERSION {
+        bail!(
+            "unsupported schema version {}, expected {}",
+            version,
+            SCHEMA_VERSION
+        );
+    }
+
+    Ok(serde_json::from_str(json)?)
+}
+
+#[cfg(test)]
+fn parse(src: &str) -> Vec<Item> {
+    let tokens = crate::lexer::lexer::lex(src).unwrap();
+    let program =
+        crate::parser::parser::parse_program(&mut std::collections::VecDeque::from(tokens));
+    program.into_result().unwrap()
+}
+
+#[test]
+fn tokens_serialize_with_positions() {
+    let tokens = crate::lexer::lexer::lex("<Text>").unwrap();
+    let json = serde_json::to_string(&TokensDocument::new("app.flutter", tokens)).unwrap();
+
+    assert_eq!(
+        json,
+        concat!(
+            r#"{"version":9,"file":"app.flutter","tokens":["#,
+            r#"{"kind":{"type":"LessThan"},"start":0,"end":1,"line":1},"#,
+            r#"{"kind":{"type":"Identifier","value":"Text"},"start":1,"end":5,"line":1},"#,
+            r#"{"kind":{"type":"GreaterThan"},"start":5,"end":6,"line":1},"#,
+            r#"{"kind":{"type":"Newline"},"start":6,"end":6,"line":1}]}"#
+        )
+    );
+}
+
+#[test]
+fn ast_round_trips_through_json() {
+    let src = include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/golden/expressions.flutter"
+    ));
+    let document = AstDocument::new("expressions.flutter", parse(src), Vec::new());
+
+    let json = serde_json::to_string(&document).unwrap();
+    assert_eq!(from_json::<AstDocument>(&json).unwrap(), document);
+}
+
+#[test]
+fn expressions_are_tagged_with_their_kind() {
+    let document = AstDocument::new("app.flutter", parse("<Text> count"), Vec::new());
+    let json = serde_json::to_value(&document).unwrap();
+
+    assert_eq!(
+        json["items"][0]["content"]["kind"],
+        serde_json::json!({ "type": "Identifier", "value": "count" })
+    );
+}
+
+#[test]
+fn other_schema_versions_are_rejected() {
+    let json = r#"{"version":1,"file":"app.flutter","items":[]}"#;
+
+    assert!(from_json::<AstDocument>(json).is_err());
+}
@@ -0,0 +1,7 @@
+@override
+Widget build(BuildContext context) {
+  return ListView.builder(
+    itemCount: controller.history.length,
+    itemBuilder: (context, index) => Text(controller.history[index]),
+  );
+}
@@ -0,0 +1,11 @@
+@override
+Widget build(BuildContext context) {
+  return Button(
+    children: [
+      Text1(),
+      Text2(),
+      Text3(),
+      Text4(),
+    ],
+  );
+}
@@ -0,0 +1,26 @@
+import 'package:flutter/material.dart';
+// Shared header, see header.flutter
+import 'header.dart';
+
+// The greeting page, shown
+// after logging in.
+class Greeting extends StatelessWidget {
+  const Greeting({super.key, required this.name});
+
+  final String name;
+
+  @override
+  Widget build(BuildContext context) {
+    // laid out top to bottom
+    return Column(
+      children: [
+        Header(),
+        /* the name comes from the account */
+        Text("Hello $name"),
+        Icon(Icons.waving_hand, semanticLabel: "wave"),
+        // trailing comments go to the next line
+        Text("Bye"),
+      ],
+    );
+  }
+}
@@ -0,0 +1,44 @@
+import 'package:flutter/material.dart';
+
+class FeedPage extends StatelessWidget {
+  const FeedPage({super.key, required this.controller});
+
+  final FeedController controller;
+
+  @override
+  Widget build(BuildContext context) {
+    return Scaffold(
+      appBar: AppBar(
+        title: Text("Feed"),
+      ),
+      body: ListenableBuilder(
+        listenable: controller,
+        builder: (context, child) => Center(
+          child: controller.loading
+              ? CircularProgressIndicator()
+              : controller.error != null
+                    ? Text("Failed: ${controller.error}")
+                    : Column(
+                        children: [
+                          Text("${controller.items.length} items"),
+                          if (controller.items.isEmpty)
+                            Text("Nothing yet")
+                          else if (controller.items.length >= controller.limit)
+                            Text("That's all for now"),
+                          // offer a refresh unless one is running
+                          if (!controller.refreshing && controller.canRefresh) ...[
+                            TextButton(
+                              onPressed: controller.refresh,
+                              child: Text("Refresh"),
+                            ),
+                            Text("or pull down"),
+                          ] else ...[
+                            LinearProgressIndicator(),
+                          ],
+                        ],
+                      ),
+        ),
+      ),
+    );
+  }
+}
@@ -0,0 +1,21 @@
+@override
+Widget build(BuildContext context) {
+  return Column(
+    children: [
+      Text("Counter"),
+      Container(
+        child: GlowingBox(
+          child: WavingAnimation(
+            child: Text("Happy hacking!"),
+          ),
+        ),
+      ),
+      Button(
+        onTap: controller.increment,
+        child: Text("Increment"),
+      ),
+      Icon(42),
+      Text(controller.title),
+    ],
+  );
+}
@@ -0,0 +1,24 @@
+@override
+Widget build(BuildContext context) {
+  return Scaffold(
+    appBar: AppBar(
+      title: Text("Counter"),
+    ),
+    body: Column(
+      children: [
+        Button(
+          child: Text("+"),
+        ),
+        Button(
+          child: Text("-"),
+        ),
+        Header(),
+        ListView(
+          children: [
+            Text("Item"),
+          ],
+        ),
+      ],
+    ),
+  );
+}
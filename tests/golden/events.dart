@@ -0,0 +1,20 @@
+@override
+Widget build(BuildContext context) {
+  return Column(
+    children: [
+      Container(
+        color: Colors.yellow.shade100,
+        child: ElevatedButton(
+          onPressed: controller.increment,
+          child: Text("+1"),
+        ),
+      ),
+      InkWell(
+        onTap: controller.reset,
+        onLongPress: controller.clear,
+        child: Text("Reset"),
+      ),
+      TextField(onChanged: controller.rename, onSubmitted: controller.save),
+    ],
+  );
+}
@@ -0,0 +1,16 @@
+@override
+Widget build(BuildContext context) {
+  return Column(
+    children: [
+      FittedBox(
+        child: Text("Counter: ${controller.counter}"),
+      ),
+      Text(controller.history[controller.history.length - 1].toUpperCase()),
+      Opacity(opacity: (controller.progress + 1) / 2),
+      Visibility(
+        visible: (controller.progress < 1),
+        child: LinearProgressIndicator(),
+      ),
+    ],
+  );
+}
@@ -0,0 +1,14 @@
+import 'package:flutter/material.dart';
+import './counter_page.controller.dart';
+import './widgets/glowing_box.dart';
+
+class CounterPage extends StatelessWidget {
+  const CounterPage({super.key, required this.controller});
+
+  final CounterPageController controller;
+
+  @override
+  Widget build(BuildContext context) {
+    return GlowingBox();
+  }
+}
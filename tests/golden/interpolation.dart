@@ -0,0 +1,25 @@
+import 'package:flutter/material.dart';
+
+class CounterPage extends StatelessWidget {
+  const CounterPage({super.key, required this.controller, required this.name});
+
+  final CounterPageController controller;
+  final String name;
+
+  @override
+  Widget build(BuildContext context) {
+    return Column(
+      children: [
+        ListenableBuilder(
+          listenable: controller,
+          builder: (context, child) => Text("Counter: ${controller.counter}"),
+        ),
+        Text("Hello $name, you have ${name}s \"unread\" \$5 \\ messages"),
+        ListenableBuilder(
+          listenable: controller,
+          builder: (context, child) => Text("Total:\t${controller.total}\n"),
+        ),
+      ],
+    );
+  }
+}
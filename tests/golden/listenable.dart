@@ -0,0 +1,73 @@
+import 'package:flutter/material.dart';
+
+class PlayerBar extends StatelessWidget {
+  const PlayerBar({super.key, required this.player, required this.volume, required this.title});
+
+  final PlayerController player;
+  final ValueNotifier<double> volume;
+  final String title;
+
+  @override
+  Widget build(BuildContext context) {
+    return ListenableBuilder(
+      listenable: player,
+      builder: (context, child) => Scaffold(
+        // an app bar must stay a PreferredSizeWidget, the Scaffold rebuilds instead
+        appBar: AppBar(
+          backgroundColor: player.color,
+          title: Text("$title: ${player.track}"),
+        ),
+        body: Column(
+          children: [
+            ListenableBuilder(
+              listenable: volume,
+              builder: (context, child) => Text("Volume: ${volume.value}"),
+            ),
+            ListenableBuilder(
+              listenable: volume,
+              builder: (context, child) => Text("${player.position} / ${volume.value}"),
+            ),
+            ListenableBuilder(
+              listenable: volume,
+              builder: (context, child) => Slider(value: volume.value, onChanged: volume.set),
+            ),
+            // repainted by the seek bar
+            Text(player.position),
+          ],
+        ),
+      ),
+    );
+  }
+}
+
+class VolumeLabel extends StatefulWidget {
+  const VolumeLabel({super.key, required this.volume, required this.muted});
+
+  final ValueNotifier<double> volume;
+  final ValueNotifier<bool> muted;
+
+  @override
+  State<VolumeLabel> createState() => _VolumeLabelState();
+}
+
+class _VolumeLabelState extends State<VolumeLabel> {
+  double peak = 0;
+
+  @override
+  Widget build(BuildContext context) {
+    return ListenableBuilder(
+      listenable: widget.muted,
+      builder: (context, child) => widget.muted.value
+          ? Icon(Icons.volume_off)
+          : InkWell(
+              onTap: () => setState(() => peak = widget.volume.value),
+              child: ListenableBuilder(
+                listenable: widget.volume,
+                builder: (context, child) => Text(
+                  "${widget.volume.value} (peak $peak, ${widget.muted.value})",
+                ),
+              ),
+            ),
+    );
+  }
+}
@@ -0,0 +1,45 @@
+import 'package:flutter/material.dart';
+
+class HistoryPage extends StatelessWidget {
+  const HistoryPage({super.key, required this.controller});
+
+  final HistoryController controller;
+
+  @override
+  Widget build(BuildContext context) {
+    return Scaffold(
+      body: ListenableBuilder(
+        listenable: controller,
+        builder: (context, child) => Column(
+          children: [
+            Wrap(
+              children: [
+                for (final tag in controller.tags) Text(tag, key: ValueKey(tag)),
+              ],
+            ),
+            for (final (index, entry) in (controller.recent + controller.pinned).indexed) ...[
+              Text("$index: ${entry.title}"),
+              Divider(),
+            ],
+            for (final entry in controller.drafts)
+              if (entry.visible) Text(entry.title),
+            Expanded(
+              // built as they scroll into view
+              child: ListView.builder(
+                itemCount: controller.history.length,
+                itemBuilder: (context, index) {
+                  final entry = controller.history[index];
+                  return ListTile(
+                    onTap: controller.open,
+                    title: Text(entry.title),
+                    key: ValueKey(entry.id),
+                  );
+                },
+              ),
+            ),
+          ],
+        ),
+      ),
+    );
+  }
+}
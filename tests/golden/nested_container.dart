@@ -0,0 +1,12 @@
+@override
+Widget build(BuildContext context) {
+  return Container(
+    child: GlowingBox(
+      child: WavingAnimation(
+        child: FittedBox(
+          child: Text("Hi"),
+        ),
+      ),
+    ),
+  );
+}
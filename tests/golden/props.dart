@@ -0,0 +1,19 @@
+@override
+Widget build(BuildContext context) {
+  return ListView(
+    children: [
+      Text("Item", maxLines: controller.history.length),
+      IconButton(
+        tooltip: "Add",
+        iconSize: 24 * controller.scale,
+        color: Colors.red,
+        onPressed: controller.add,
+        icon: Icon(Icons.add),
+      ),
+      Padding(
+        padding: EdgeInsets.all(4),
+        child: SizedBox(height: -8, width: (controller.width)),
+      ),
+    ],
+  );
+}
@@ -0,0 +1,24 @@
+@override
+Widget build(BuildContext context) {
+  return Container(
+    color: Colors.yellow.shade100,
+    child: Scaffold(
+      appBar: AppBar(
+        title: Text("Counter"),
+      ),
+      body: Align(
+        alignment: Alignment.center,
+        child: Padding(
+          padding: EdgeInsets.all(10),
+          child: Column(
+            children: [
+              Header(),
+              Icon(Icons.mood, semanticLabel: "Happy hacking!"),
+            ],
+          ),
+        ),
+      ),
+      floatingActionButton: FloatingActionButton(onPressed: controller.increment),
+    ),
+  );
+}
@@ -0,0 +1,33 @@
+import 'package:flutter/material.dart';
+
+class Counter extends StatefulWidget {
+  const Counter({super.key, required this.step, required this.label});
+
+  final int step;
+  final String label;
+
+  @override
+  State<Counter> createState() => _CounterState();
+}
+
+class _CounterState extends State<Counter> {
+  // how many times the button was tapped
+  int count = 0;
+  late int total = widget.step * 10;
+  String query = "";
+
+  @override
+  Widget build(BuildContext context) {
+    return Column(
+      children: [
+        Text("${widget.label}: $count of $total"),
+        TextField(onChanged: (value) => setState(() => query = value)),
+        ElevatedButton(
+          onPressed: () => setState(() => count += widget.step),
+          onLongPress: () => setState(() => count = 0),
+          child: Text("Add ${widget.step}"),
+        ),
+      ],
+    );
+  }
+}
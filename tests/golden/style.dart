@@ -0,0 +1,34 @@
+@override
+Widget build(BuildContext context) {
+  return Container(
+    color: Colors.yellow.shade100,
+    child: Scaffold(
+      body: Align(
+        alignment: Alignment.center,
+        child: Padding(
+          padding: EdgeInsets.all(10),
+          child: Column(
+            children: [
+              Container(
+                color: Colors.red.shade100,
+                child: Padding(
+                  padding: EdgeInsets.symmetric(horizontal: 8, vertical: 4),
+                  child: Button(
+                    child: Padding(
+                      padding: EdgeInsets.all(2),
+                      child: SizedBox(
+                        width: 24,
+                        height: 24,
+                        child: Icon(Icons.add),
+                      ),
+                    ),
+                  ),
+                ),
+              ),
+            ],
+          ),
+        ),
+      ),
+    ),
+  );
+}
@@ -0,0 +1,24 @@
+import 'package:flutter/material.dart';
+
+class CounterPage extends StatelessWidget {
+  const CounterPage({super.key, required this.controller, required this.history});
+
+  final CounterPageController controller;
+  final List<String> history;
+
+  @override
+  Widget build(BuildContext context) {
+    return Scaffold(
+      body: Column(
+        children: [
+          Button(
+            child: Text("+"),
+          ),
+          ListView(
+            children: [],
+          ),
+        ],
+      ),
+    );
+  }
+}